@@ -0,0 +1,38 @@
+use std::process;
+
+use risp::lexer::comments;
+use risp::parser::parse_program;
+use risp::pretty;
+
+// 一行の幅の上限
+const WIDTH: usize = 80;
+
+// .risp ファイルを読んで、式ごとに pretty で整えて表示する
+// 式のあいだは空行一つで区切る
+// AST にはコメントが残らないので、コメントのあるファイルは書き直さずにエラーにする
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: fmt <file>");
+            process::exit(2);
+        }
+    };
+    let source = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path, err);
+        process::exit(1);
+    });
+    if let Some(pos) = comments(&source).first() {
+        eprintln!(
+            "{}: cannot format a file with comments: the comment at {} would be lost",
+            path, pos
+        );
+        process::exit(1);
+    }
+    let forms = parse_program(&source).unwrap_or_else(|err| {
+        eprintln!("{}: parse error: {}", path, err);
+        process::exit(1);
+    });
+    let formatted: Vec<String> = forms.iter().map(|form| pretty(form, WIDTH)).collect();
+    println!("{}", formatted.join("\n\n"));
+}
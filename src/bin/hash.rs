@@ -7,7 +7,7 @@ fn main() {
 
     let key = vec![3, 4, 5];
     let val = vec!["a", "b", "c"];
-    let mut m2: HashMap<_, _> = key.into_iter().zip(val).collect();
+    let m2: HashMap<_, _> = key.into_iter().zip(val).collect();
 
     m1.extend(m2.clone());
     dbg!(m1);
@@ -1,20 +1,18 @@
-use std::collections::HashMap;
+use risp::{ast, eval, Env, EvalError};
 
-use risp::{ast, eval};
-
-fn main() {
-    let mut env = HashMap::new();
+fn main() -> Result<(), EvalError> {
+    let env = Env::new();
     // let plus_two = ast!((Define plus_two (Func (x) (+ x 2))));
-    // eval(plus_two, &mut env);
+    // eval(plus_two, &env);
 
     // let app = ast!((Apply plus_two 3));
-    // let obj = eval(app, &mut env);
+    // let obj = eval(app, &env);
     // dbg!(&obj);
 
     // let y = ast!((Define y 10));
-    // eval(y, &mut env);
+    // eval(y, &env);
     // let app = ast!((Apply plus_two y));
-    // let obj = eval(app, &mut env);
+    // let obj = eval(app, &env);
     // dbg!(obj);
     let sum = ast!(
     (Define sum
@@ -23,8 +21,9 @@ fn main() {
                 1
                 (+ n (Apply sum (- n 1)))
             ))));
-    eval(sum, &mut env);
+    eval(sum, &env)?;
     let sum_app = ast!((Apply sum 100));
-    let res = eval(sum_app, &mut env);
+    let res = eval(sum_app, &env)?;
     dbg!(&res);
+    Ok(())
 }
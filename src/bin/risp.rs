@@ -0,0 +1,42 @@
+use std::process;
+
+use risp::parser::parse_program;
+use risp::{eval_program, prelude, Env};
+
+// .risp ファイルを読んで評価し、最後の式の値を表示する
+// 組み込み関数の prelude を束縛した環境で評価する
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: risp <file>");
+            process::exit(2);
+        }
+    };
+    let source = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path, err);
+        process::exit(1);
+    });
+    let forms = parse_program(&source).unwrap_or_else(|err| {
+        eprintln!("{}: parse error: {}", path, err);
+        process::exit(1);
+    });
+    // 再帰する関数は Rust の再帰で評価されるのでスタックは大きめにとる
+    // Env は Rc なので、スレッドの外には文字列にしてから返す
+    let result = std::thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(|| match eval_program(forms, &Env::from(prelude())) {
+            Ok(obj) => Ok(obj.to_string()),
+            Err(err) => Err(err.to_string()),
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    match result {
+        Ok(obj) => println!("{}", obj),
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            process::exit(1);
+        }
+    }
+}
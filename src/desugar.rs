@@ -0,0 +1,76 @@
+use crate::{map_ast, AST};
+
+// 節を後ろから順に If の els に入れていく
+// 条件を上から順に評価して最初に真になった節の本体を評価するので、評価の順番は Cond と同じ
+fn cond_to_if(clauses: Vec<(AST, AST)>, default: AST) -> AST {
+    clauses
+        .into_iter()
+        .rev()
+        .fold(default, |els, (cond, then)| AST::If {
+            cond: Box::new(cond),
+            then: Box::new(then),
+            els: Box::new(els),
+        })
+}
+
+fn lower(ast: AST) -> AST {
+    match ast {
+        AST::Cond { clauses, default } => cond_to_if(clauses, *default),
+        ast => ast,
+    }
+}
+
+// 評価器の中心にある形だけを使うように書き換える
+// Cond は入れ子の If になる
+// Begin は評価の順番とスコープをそのまま表せるほかの形がないので、中心の形として残す
+pub fn desugar(ast: AST) -> AST {
+    map_ast(ast, &mut lower)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast, eval, Env};
+
+    #[test]
+    fn test_desugar_cond() {
+        assert_eq!(
+            desugar(ast!((Cond ((< x 0) "negative") ((== x 0) "zero") "positive"))),
+            ast!((If (< x 0) "negative" (If (== x 0) "zero" "positive")))
+        );
+        assert_eq!(desugar(ast!((Cond 1))), ast!(1));
+        // 入れ子になった Cond も書き換える
+        assert_eq!(
+            desugar(ast!((Func (x) (Cond ((Cond (x true) false) 1) 2)))),
+            ast!((Func (x) (If (If x true false) 1 2)))
+        );
+    }
+
+    #[test]
+    fn test_desugar_preserve() {
+        let core = ast!((Begin (Define x 1) (If (< x 2) (Set! x 2) nil) x));
+        assert_eq!(desugar(core.clone()), core);
+
+        let program = ast!(
+            (Begin
+                (Define sign (Func (n) (Cond ((< n 0) (- 0 1)) ((== n 0) 0) 1)))
+                (list (Apply sign (- 0 5)) (Apply sign 0) (Apply sign 5))));
+        assert_eq!(
+            eval(desugar(program.clone()), &Env::new()),
+            eval(program, &Env::new())
+        );
+
+        // 節の中の Define は Cond でも If でも外に残らない
+        let programs = [
+            ast!((Begin (Cond (true (Define y 1)) 0) y)),
+            ast!((Begin (Define y 0) (Cond ((== y 0) (Begin (Define y 1) y)) 2))),
+            ast!((Begin (Define y 0) (Cond (false 1) (Begin (Define y 2) y)) y)),
+        ];
+        for program in programs {
+            assert_eq!(
+                eval(desugar(program.clone()), &Env::new()),
+                eval(program, &Env::new())
+            );
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::Object;
+
+// 変数の束縛を持つスコープ
+// 子のスコープは親への参照を持っていて、見つからなければ親をたどる
+// clone しても中身はコピーされず同じスコープを指す
+// 関数は定義されたスコープを持つので、(Define f (Func ...)) をすると
+// スコープと関数が互いを指し合い、Env を捨てただけでは解放されない
+// 使い終わったスコープは Env::clear で束縛を消すと解放される
+#[derive(Clone, Default)]
+pub struct Env(Rc<RefCell<Frame>>);
+
+#[derive(Default)]
+struct Frame {
+    vars: HashMap<String, Object>,
+    parent: Option<Env>,
+    // Env::with_scope で作ったスコープでは、vars の代わりにこちらを読み書きする
+    scope: Option<Rc<RefCell<dyn Scope>>>,
+}
+
+// 変数を読み書きする先
+// Env::with_scope で Env にすると、HashMap や外部の設定などを環境として評価できる
+pub trait Scope {
+    fn get(&self, k: &str) -> Option<Object>;
+    fn set(&mut self, k: String, v: Object);
+}
+
+impl Scope for HashMap<String, Object> {
+    fn get(&self, k: &str) -> Option<Object> {
+        HashMap::get(self, k).cloned()
+    }
+
+    fn set(&mut self, k: String, v: Object) {
+        self.insert(k, v);
+    }
+}
+
+impl Scope for Env {
+    fn get(&self, k: &str) -> Option<Object> {
+        Env::get(self, k)
+    }
+
+    // Set! と同じく束縛されている一番近いスコープを書き換え、どこにもなければこのスコープに束縛する
+    fn set(&mut self, k: String, v: Object) {
+        if !Env::set(self, &k, v.clone()) {
+            self.define(k, v);
+        }
+    }
+}
+
+// Env::snapshot で保存した、あるスコープとその親たちの束縛
+pub struct EnvSnapshot(Vec<(Env, HashMap<String, Object>)>);
+
+impl Env {
+    pub fn new() -> Self {
+        Env::default()
+    }
+
+    // 束縛を自分では持たず、すべて scope に読み書きする一番外側のスコープを作る
+    // Define も Set! も scope に書き込まれ、評価中に scope が変われば関数の中からもすぐに見える
+    // scope の束縛は snapshot と restore では保存も復元もされない
+    pub fn with_scope(scope: Rc<RefCell<dyn Scope>>) -> Self {
+        Env(Rc::new(RefCell::new(Frame {
+            scope: Some(scope),
+            ..Frame::default()
+        })))
+    }
+
+    // self を親に持つ空のスコープを作る
+    pub fn child(&self) -> Self {
+        Env(Rc::new(RefCell::new(Frame {
+            parent: Some(self.clone()),
+            ..Frame::default()
+        })))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        let frame = self.0.borrow();
+        if let Some(scope) = &frame.scope {
+            return scope.borrow().get(name);
+        }
+        match frame.vars.get(name) {
+            Some(obj) => Some(obj.clone()),
+            None => frame.parent.as_ref()?.get(name),
+        }
+    }
+
+    // 親ではなくこのスコープに束縛する
+    pub fn define(&self, name: String, value: Object) {
+        let mut frame = self.0.borrow_mut();
+        match &frame.scope {
+            Some(scope) => scope.borrow_mut().set(name, value),
+            None => {
+                frame.vars.insert(name, value);
+            }
+        }
+    }
+
+    // すでに束縛されている一番近いスコープの値を書き換える
+    // どこにも束縛されていなければ false を返す
+    pub fn set(&self, name: &str, value: Object) -> bool {
+        let mut frame = self.0.borrow_mut();
+        if let Some(scope) = &frame.scope {
+            let bound = scope.borrow().get(name).is_some();
+            if bound {
+                scope.borrow_mut().set(name.to_string(), value);
+            }
+            return bound;
+        }
+        if let Some(slot) = frame.vars.get_mut(name) {
+            *slot = value;
+            return true;
+        }
+        match &frame.parent {
+            Some(parent) => parent.set(name, value),
+            None => false,
+        }
+    }
+
+    // このスコープの束縛をすべて消す
+    // 束縛された関数とこのスコープの間の循環がなくなり、参照が残っていなければ解放される
+    pub fn clear(&self) {
+        let vars = std::mem::take(&mut self.0.borrow_mut().vars);
+        // 関数を捨てると別のスコープも解放されうるので、借用を返してから捨てる
+        drop(vars);
+    }
+
+    // このスコープと親のスコープの今の束縛を保存する
+    // 関数が持っている別のスコープの中身は保存しない
+    pub fn snapshot(&self) -> EnvSnapshot {
+        let mut frames = vec![];
+        let mut env = Some(self.clone());
+        while let Some(current) = env {
+            let (vars, parent) = {
+                let frame = current.0.borrow();
+                (frame.vars.clone(), frame.parent.clone())
+            };
+            frames.push((current, vars));
+            env = parent;
+        }
+        EnvSnapshot(frames)
+    }
+
+    // snapshot を取ったときの束縛に戻す
+    // そのあとに Define した名前は消え、Set! した値は元に戻る
+    pub fn restore(&self, snapshot: EnvSnapshot) {
+        debug_assert!(snapshot.0.first().is_some_and(|(env, _)| env == self));
+        for (env, vars) in snapshot.0 {
+            env.0.borrow_mut().vars = vars;
+        }
+    }
+}
+
+// 関数は自分を定義したスコープを持つので、中身を比較・表示すると循環してしまう
+// スコープは同じものを指しているかどうかだけで比べる
+impl PartialEq for Env {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for Env {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Env").finish_non_exhaustive()
+    }
+}
+
+impl From<HashMap<String, Object>> for Env {
+    fn from(vars: HashMap<String, Object>) -> Self {
+        Env(Rc::new(RefCell::new(Frame {
+            vars,
+            ..Frame::default()
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast, eval};
+
+    #[test]
+    fn test_call_frame_not_copied() {
+        let env = Env::new();
+        for i in 0..1000 {
+            env.define(format!("v{}", i), Object::Num(i));
+        }
+        eval(ast!((Define make (Func (n) (Func () n)))), &env).unwrap();
+        let closure = eval(ast!((Apply make 1)), &env).unwrap();
+        // 呼び出しのスコープには引数だけが入っていて、外側の束縛は親をたどって見る
+        let frame = match closure {
+            Object::Function { env: frame, .. } => frame,
+            obj => panic!("expected a function, but got {:?}", obj),
+        };
+        assert_eq!(frame.0.borrow().vars.len(), 1);
+        assert_eq!(frame.0.borrow().parent.as_ref(), Some(&env));
+        assert_eq!(frame.get("v999"), Some(Object::Num(999)));
+
+        // 呼び出しが終われば、スコープへの参照は作った関数のぶんしか残らない
+        assert_eq!(Rc::strong_count(&frame.0), 1);
+        let strong = Rc::strong_count(&env.0);
+        eval(ast!((Apply make 2)), &env).unwrap();
+        assert_eq!(Rc::strong_count(&env.0), strong);
+    }
+
+    #[test]
+    fn test_scope_set() {
+        let parent = Env::new();
+        parent.define("x".to_string(), Object::Num(1));
+        let mut child = parent.child();
+
+        // 親に束縛されている名前は、子に新しく束縛せずに親の値を書き換える
+        Scope::set(&mut child, "x".to_string(), Object::Num(2));
+        assert_eq!(parent.get("x"), Some(Object::Num(2)));
+        assert!(child.0.borrow().vars.is_empty());
+
+        // どこにも束縛されていない名前はこのスコープに束縛する
+        Scope::set(&mut child, "y".to_string(), Object::Num(3));
+        assert_eq!(child.0.borrow().vars.get("y"), Some(&Object::Num(3)));
+        assert_eq!(parent.get("y"), None);
+    }
+
+    #[test]
+    fn test_clear_frees_recursive_function() {
+        let env = Env::new();
+        eval(ast!((Define f (Func (n) (Apply f n)))), &env).unwrap();
+        let frame = Rc::downgrade(&env.0);
+
+        // f がスコープを持っているので、Env をすべて捨ててもスコープは残る
+        drop(env);
+        let leaked = Env(frame.upgrade().expect("frame is kept alive by f"));
+
+        // 束縛を消せば循環がなくなり、最後の Env を捨てると解放される
+        leaked.clear();
+        assert_eq!(leaked.get("f"), None);
+        drop(leaked);
+        assert!(frame.upgrade().is_none());
+    }
+}
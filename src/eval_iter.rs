@@ -0,0 +1,752 @@
+use crate::{
+    arrange, assertion_failed, bind_args, car, cdr, compare, cons, destructure, elements, length,
+    list, maximum, minimum, nth, quote, truthy, Env, EvalError, Object, AST,
+};
+
+// 両辺を評価したあとに計算する二項演算
+enum BinOp {
+    Add,
+    Minus,
+    Mul,
+    Div,
+    Mod,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEq,
+    GreaterEq,
+    Min,
+    Max,
+    Cons,
+    Nth,
+}
+
+// 値を一つ評価したあとに計算する単項演算
+enum UnOp {
+    Not,
+    Negate,
+    Car,
+    Cdr,
+    Length,
+    TypeOf,
+}
+
+impl UnOp {
+    fn apply(self, value: Object) -> Result<Object, EvalError> {
+        match self {
+            UnOp::Not => Ok(Object::Bool(!truthy(value)?)),
+            UnOp::Negate => -value,
+            UnOp::Car => car(value),
+            UnOp::Cdr => cdr(value),
+            UnOp::Length => length(value),
+            UnOp::TypeOf => Ok(Object::Str(value.type_name().to_string())),
+        }
+    }
+}
+
+impl BinOp {
+    fn apply(self, left: Object, right: Object) -> Result<Object, EvalError> {
+        let obj = match self {
+            BinOp::Add => (left + right)?,
+            BinOp::Minus => (left - right)?,
+            BinOp::Mul => (left * right)?,
+            BinOp::Div => (left / right)?,
+            BinOp::Mod => (left % right)?,
+            BinOp::Equal => Object::Bool(left == right),
+            BinOp::NotEqual => Object::Bool(left != right),
+            BinOp::Less => Object::Bool(compare(left, right)?.is_lt()),
+            BinOp::Greater => Object::Bool(compare(left, right)?.is_gt()),
+            BinOp::LessEq => Object::Bool(compare(left, right)?.is_le()),
+            BinOp::GreaterEq => Object::Bool(compare(left, right)?.is_ge()),
+            BinOp::Min => minimum(left, right)?,
+            BinOp::Max => maximum(left, right)?,
+            BinOp::Cons => cons(left, right),
+            BinOp::Nth => nth(left, right)?,
+        };
+        Ok(obj)
+    }
+}
+
+// 作業スタックに積む仕事
+// Eval 以外は、先に積んだ式の値が値スタックに揃ったあとにやる続きの処理
+enum Task {
+    Eval(AST, Env),
+    BinOp(BinOp),
+    UnOp(UnOp),
+    // 左辺の値を見て右辺を評価するかどうか決める
+    And(Box<AST>, Env),
+    Or(Box<AST>, Env),
+    // 右辺の値を Bool にする
+    Truthy,
+    If {
+        then: Box<AST>,
+        els: Box<AST>,
+        env: Env,
+    },
+    // 条件の値を見て、body を評価するか残りの節に進む
+    Cond {
+        body: AST,
+        rest: std::vec::IntoIter<(AST, AST)>,
+        default: Box<AST>,
+        env: Env,
+    },
+    // key の値を値スタックに残した scrutinee と比べて、body を評価するか残りの節に進む
+    Case {
+        body: AST,
+        rest: std::vec::IntoIter<(AST, AST)>,
+        default: Box<AST>,
+        env: Env,
+    },
+    // 条件の値を見て、body を評価してもう一度ループするか終わる
+    While {
+        cond: Box<AST>,
+        body: Box<AST>,
+        env: Env,
+    },
+    // 回数の値を見て DoTimesStep を始める
+    DoTimes {
+        var: String,
+        body: Box<AST>,
+        env: Env,
+    },
+    // 値スタックの一番上に直前の body の値がある状態で、次の繰り返しに進むか終わる
+    DoTimesStep {
+        var: String,
+        body: Box<AST>,
+        env: Env,
+        index: i64,
+        count: i64,
+    },
+    Define(String, Env),
+    Set(String, Env),
+    Let {
+        name: String,
+        body: Box<AST>,
+        env: Env,
+    },
+    LetList {
+        names: Vec<String>,
+        body: Box<AST>,
+        env: Env,
+    },
+    // 値スタックの一番上を表示する
+    Print,
+    // 条件の値を見て、偽なら msg を評価して AssertionFailed にする
+    Assert(Box<AST>, Env),
+    AssertionFailed,
+    // Begin や関数の本体で途中の式の値を捨てる
+    Discard,
+    Call(usize),
+    // 名前付きの引数を並べ替えてから Call する
+    CallNamed(usize, Vec<String>),
+    // 値スタックの一番上のリストを引数に展開して Call する
+    ApplyList,
+    // 値スタックの上から n 個をリストにまとめる
+    List(usize),
+    // 値スタックの上の関数とリストから、要素ごとに Call を積む
+    Map,
+    // 値スタックの上の関数と初期値とリストから畳み込みを始める
+    Fold,
+    // 値スタックの一番上にそれまでの値がある状態で、残りの要素を一つずつ func に渡す
+    FoldStep {
+        func: Object,
+        rest: std::vec::IntoIter<Object>,
+    },
+    // 評価済みの値をそのまま値スタックに積む
+    Value(Object),
+}
+
+fn push_sequence(tasks: &mut Vec<Task>, exprs: Vec<AST>, env: &Env) {
+    if exprs.is_empty() {
+        tasks.push(Task::Eval(AST::Nil, env.clone()));
+    }
+    for (i, expr) in exprs.into_iter().enumerate().rev() {
+        tasks.push(Task::Eval(expr, env.clone()));
+        if i > 0 {
+            tasks.push(Task::Discard);
+        }
+    }
+}
+
+fn push_binop(tasks: &mut Vec<Task>, op: BinOp, left: AST, right: AST, env: Env) {
+    tasks.push(Task::BinOp(op));
+    tasks.push(Task::Eval(right, env.clone()));
+    tasks.push(Task::Eval(left, env));
+}
+
+fn push_unop(tasks: &mut Vec<Task>, op: UnOp, value: AST, env: Env) {
+    tasks.push(Task::UnOp(op));
+    tasks.push(Task::Eval(value, env));
+}
+
+fn push_cond(
+    tasks: &mut Vec<Task>,
+    mut clauses: std::vec::IntoIter<(AST, AST)>,
+    default: Box<AST>,
+    env: Env,
+) {
+    match clauses.next() {
+        Some((cond, body)) => {
+            tasks.push(Task::Cond {
+                body,
+                rest: clauses,
+                default,
+                env: env.clone(),
+            });
+            tasks.push(Task::Eval(cond, env));
+        }
+        None => tasks.push(Task::Eval(*default, env.child())),
+    }
+}
+
+// scrutinee の値が値スタックの一番上にある状態で使う
+fn push_case(
+    tasks: &mut Vec<Task>,
+    mut arms: std::vec::IntoIter<(AST, AST)>,
+    default: Box<AST>,
+    env: Env,
+) {
+    match arms.next() {
+        Some((key, body)) => {
+            tasks.push(Task::Case {
+                body,
+                rest: arms,
+                default,
+                env: env.clone(),
+            });
+            tasks.push(Task::Eval(key, env));
+        }
+        None => {
+            tasks.push(Task::Eval(*default, env.child()));
+            tasks.push(Task::Discard);
+        }
+    }
+}
+
+// eval と同じ結果になるが、Rust の再帰を使わずに作業スタックと値スタックで評価する
+// 深くネストした式でもスタックオーバーフローしない
+pub fn eval_iter(ast: AST, env: &Env) -> Result<Object, EvalError> {
+    let mut tasks = vec![Task::Eval(ast, env.clone())];
+    let mut values: Vec<Object> = vec![];
+    while let Some(task) = tasks.pop() {
+        match task {
+            Task::Eval(ast, env) => match ast {
+                AST::Num(v) => values.push(Object::Num(v)),
+                AST::Float(v) => values.push(Object::Float(v)),
+                AST::Bool(b) => values.push(Object::Bool(b)),
+                AST::Str(s) => values.push(Object::Str(s)),
+                AST::Char(c) => values.push(Object::Char(c)),
+                AST::Nil => values.push(Object::Nil),
+                AST::Add(left, right) => push_binop(&mut tasks, BinOp::Add, *left, *right, env),
+                AST::Minus(left, right) => push_binop(&mut tasks, BinOp::Minus, *left, *right, env),
+                AST::Mul(left, right) => push_binop(&mut tasks, BinOp::Mul, *left, *right, env),
+                AST::Div(left, right) => push_binop(&mut tasks, BinOp::Div, *left, *right, env),
+                AST::Mod(left, right) => push_binop(&mut tasks, BinOp::Mod, *left, *right, env),
+                AST::Equal(left, right) => push_binop(&mut tasks, BinOp::Equal, *left, *right, env),
+                AST::NotEqual(left, right) => {
+                    push_binop(&mut tasks, BinOp::NotEqual, *left, *right, env)
+                }
+                AST::Less(left, right) => push_binop(&mut tasks, BinOp::Less, *left, *right, env),
+                AST::Greater(left, right) => {
+                    push_binop(&mut tasks, BinOp::Greater, *left, *right, env)
+                }
+                AST::LessEq(left, right) => {
+                    push_binop(&mut tasks, BinOp::LessEq, *left, *right, env)
+                }
+                AST::GreaterEq(left, right) => {
+                    push_binop(&mut tasks, BinOp::GreaterEq, *left, *right, env)
+                }
+                AST::Min(left, right) => push_binop(&mut tasks, BinOp::Min, *left, *right, env),
+                AST::Max(left, right) => push_binop(&mut tasks, BinOp::Max, *left, *right, env),
+                AST::And(left, right) => {
+                    tasks.push(Task::And(right, env.clone()));
+                    tasks.push(Task::Eval(*left, env));
+                }
+                AST::Or(left, right) => {
+                    tasks.push(Task::Or(right, env.clone()));
+                    tasks.push(Task::Eval(*left, env));
+                }
+                AST::Not(value) => push_unop(&mut tasks, UnOp::Not, *value, env),
+                AST::Negate(value) => push_unop(&mut tasks, UnOp::Negate, *value, env),
+                AST::Cons(head, tail) => push_binop(&mut tasks, BinOp::Cons, *head, *tail, env),
+                AST::Car(value) => push_unop(&mut tasks, UnOp::Car, *value, env),
+                AST::Cdr(value) => push_unop(&mut tasks, UnOp::Cdr, *value, env),
+                AST::Length(value) => push_unop(&mut tasks, UnOp::Length, *value, env),
+                AST::Nth(value, index) => push_binop(&mut tasks, BinOp::Nth, *value, *index, env),
+                AST::TypeOf(value) => push_unop(&mut tasks, UnOp::TypeOf, *value, env),
+                AST::If { cond, then, els } => {
+                    tasks.push(Task::If {
+                        then,
+                        els,
+                        env: env.clone(),
+                    });
+                    tasks.push(Task::Eval(*cond, env));
+                }
+                AST::Cond { clauses, default } => {
+                    push_cond(&mut tasks, clauses.into_iter(), default, env)
+                }
+                AST::Case {
+                    scrutinee,
+                    arms,
+                    default,
+                } => {
+                    push_case(&mut tasks, arms.into_iter(), default, env.clone());
+                    tasks.push(Task::Eval(*scrutinee, env));
+                }
+                AST::While { cond, body } => {
+                    tasks.push(Task::While {
+                        cond: cond.clone(),
+                        body,
+                        env: env.clone(),
+                    });
+                    tasks.push(Task::Eval(*cond, env));
+                }
+                AST::DoTimes { count, var, body } => {
+                    tasks.push(Task::DoTimes {
+                        var,
+                        body,
+                        env: env.clone(),
+                    });
+                    tasks.push(Task::Eval(*count, env));
+                }
+                AST::Define { name, value } => {
+                    tasks.push(Task::Define(name, env.clone()));
+                    tasks.push(Task::Eval(*value, env));
+                }
+                AST::Set { name, value } => {
+                    tasks.push(Task::Set(name, env.clone()));
+                    tasks.push(Task::Eval(*value, env));
+                }
+                AST::Ident(id) => match env.get(&id) {
+                    Some(obj) => values.push(obj),
+                    None => return Err(EvalError::Undefined(id)),
+                },
+                AST::Begin(exprs) => push_sequence(&mut tasks, exprs, &env),
+                AST::Let { name, value, body } => {
+                    tasks.push(Task::Let {
+                        name,
+                        body,
+                        env: env.clone(),
+                    });
+                    tasks.push(Task::Eval(*value, env));
+                }
+                AST::LetList { names, value, body } => {
+                    tasks.push(Task::LetList {
+                        names,
+                        body,
+                        env: env.clone(),
+                    });
+                    tasks.push(Task::Eval(*value, env));
+                }
+                AST::LetRec { bindings, body } => {
+                    let frame = env.child();
+                    for (name, _) in &bindings {
+                        frame.define(name.clone(), Object::Nil);
+                    }
+                    // 値を順に評価して束縛してから body を評価する
+                    tasks.push(Task::Eval(*body, frame.clone()));
+                    for (name, value) in bindings.into_iter().rev() {
+                        tasks.push(Task::Discard);
+                        tasks.push(Task::Define(name, frame.clone()));
+                        tasks.push(Task::Eval(value, frame.clone()));
+                    }
+                }
+                AST::Function { params, rest, body } => values.push(Object::Function {
+                    params,
+                    rest,
+                    body: body.into(),
+                    env,
+                }),
+                AST::Print(value) => {
+                    tasks.push(Task::Print);
+                    tasks.push(Task::Eval(*value, env));
+                }
+                AST::Assert { cond, msg } => {
+                    tasks.push(Task::Assert(msg, env.clone()));
+                    tasks.push(Task::Eval(*cond, env));
+                }
+                AST::List(items) => {
+                    tasks.push(Task::List(items.len()));
+                    for item in items.into_iter().rev() {
+                        tasks.push(Task::Eval(item, env.clone()));
+                    }
+                }
+                AST::Map { func, list } => {
+                    tasks.push(Task::Map);
+                    tasks.push(Task::Eval(*list, env.clone()));
+                    tasks.push(Task::Eval(*func, env));
+                }
+                AST::Fold { func, init, list } => {
+                    tasks.push(Task::Fold);
+                    tasks.push(Task::Eval(*list, env.clone()));
+                    tasks.push(Task::Eval(*init, env.clone()));
+                    tasks.push(Task::Eval(*func, env));
+                }
+                AST::Quote(datum) => values.push(quote(&datum)?),
+                AST::Apply {
+                    fn_lit,
+                    args,
+                    named,
+                } => {
+                    // fn_lit, 引数, 名前付きの引数の順に評価されるように逆順に積む
+                    if named.is_empty() {
+                        tasks.push(Task::Call(args.len()));
+                    } else {
+                        tasks.push(Task::CallNamed(
+                            args.len(),
+                            named.iter().map(|(name, _)| name.clone()).collect(),
+                        ));
+                    }
+                    for (_, arg) in named.into_iter().rev() {
+                        tasks.push(Task::Eval(arg, env.clone()));
+                    }
+                    for arg in args.into_iter().rev() {
+                        tasks.push(Task::Eval(arg, env.clone()));
+                    }
+                    tasks.push(Task::Eval(*fn_lit, env));
+                }
+                AST::ApplyList { fn_lit, args } => {
+                    tasks.push(Task::ApplyList);
+                    tasks.push(Task::Eval(*args, env.clone()));
+                    tasks.push(Task::Eval(*fn_lit, env));
+                }
+            },
+            Task::BinOp(op) => {
+                let right = values.pop().unwrap();
+                let left = values.pop().unwrap();
+                values.push(op.apply(left, right)?);
+            }
+            Task::UnOp(op) => {
+                let value = values.pop().unwrap();
+                values.push(op.apply(value)?);
+            }
+            Task::And(right, env) => {
+                if truthy(values.pop().unwrap())? {
+                    tasks.push(Task::Truthy);
+                    tasks.push(Task::Eval(*right, env));
+                } else {
+                    values.push(Object::Bool(false));
+                }
+            }
+            Task::Or(right, env) => {
+                if truthy(values.pop().unwrap())? {
+                    values.push(Object::Bool(true));
+                } else {
+                    tasks.push(Task::Truthy);
+                    tasks.push(Task::Eval(*right, env));
+                }
+            }
+            Task::Truthy => {
+                let value = values.pop().unwrap();
+                values.push(Object::Bool(truthy(value)?));
+            }
+            Task::If { then, els, env } => {
+                let branch = if truthy(values.pop().unwrap())? {
+                    then
+                } else {
+                    els
+                };
+                tasks.push(Task::Eval(*branch, env.child()));
+            }
+            Task::Cond {
+                body,
+                rest,
+                default,
+                env,
+            } => {
+                if truthy(values.pop().unwrap())? {
+                    tasks.push(Task::Eval(body, env.child()));
+                } else {
+                    push_cond(&mut tasks, rest, default, env);
+                }
+            }
+            Task::Case {
+                body,
+                rest,
+                default,
+                env,
+            } => {
+                let key = values.pop().unwrap();
+                if values.last() == Some(&key) {
+                    values.pop();
+                    tasks.push(Task::Eval(body, env.child()));
+                } else {
+                    push_case(&mut tasks, rest, default, env);
+                }
+            }
+            Task::While { cond, body, env } => {
+                if truthy(values.pop().unwrap())? {
+                    tasks.push(Task::Eval(
+                        AST::While {
+                            cond,
+                            body: body.clone(),
+                        },
+                        env.clone(),
+                    ));
+                    tasks.push(Task::Discard);
+                    tasks.push(Task::Eval(*body, env));
+                } else {
+                    values.push(Object::Nil);
+                }
+            }
+            Task::DoTimes { var, body, env } => {
+                let count = i64::try_from(values.pop().unwrap())?;
+                values.push(Object::Nil);
+                tasks.push(Task::DoTimesStep {
+                    var,
+                    body,
+                    env,
+                    index: 0,
+                    count,
+                });
+            }
+            Task::DoTimesStep {
+                var,
+                body,
+                env,
+                index,
+                count,
+            } => {
+                if index < count {
+                    values.pop();
+                    let frame = env.child();
+                    frame.define(var.clone(), Object::Num(index));
+                    tasks.push(Task::DoTimesStep {
+                        var,
+                        body: body.clone(),
+                        env,
+                        index: index + 1,
+                        count,
+                    });
+                    tasks.push(Task::Eval(*body, frame));
+                }
+            }
+            Task::Define(name, env) => {
+                env.define(name, values.pop().unwrap());
+                values.push(Object::Nil);
+            }
+            Task::Set(name, env) => {
+                let value = values.last().unwrap().clone();
+                if !env.set(&name, value) {
+                    return Err(EvalError::Undefined(name));
+                }
+            }
+            Task::Let { name, body, env } => {
+                let frame = env.child();
+                frame.define(name, values.pop().unwrap());
+                tasks.push(Task::Eval(*body, frame));
+            }
+            Task::LetList { names, body, env } => {
+                let frame = destructure(&names, values.pop().unwrap(), &env)?;
+                tasks.push(Task::Eval(*body, frame));
+            }
+            Task::Print => println!("{}", values.last().unwrap()),
+            Task::Assert(msg, env) => {
+                if truthy(values.pop().unwrap())? {
+                    values.push(Object::Nil);
+                } else {
+                    tasks.push(Task::AssertionFailed);
+                    tasks.push(Task::Eval(*msg, env));
+                }
+            }
+            Task::AssertionFailed => return Err(assertion_failed(values.pop().unwrap())),
+            Task::Discard => {
+                values.pop();
+            }
+            Task::Call(argc) => {
+                let args = values.split_off(values.len() - argc);
+                match values.pop().unwrap() {
+                    Object::Function {
+                        params,
+                        rest,
+                        body,
+                        env: captured,
+                    } => {
+                        let frame = captured.child();
+                        bind_args(&frame, params, rest, args)?;
+                        push_sequence(&mut tasks, body.to_vec(), &frame);
+                    }
+                    Object::Native { func, .. } => values.push(func(args)?),
+                    obj => return Err(EvalError::NotApplicable(obj)),
+                }
+            }
+            Task::CallNamed(argc, names) => {
+                let named = values.split_off(values.len() - names.len());
+                let args = values.split_off(values.len() - argc);
+                let named = names.into_iter().zip(named).collect();
+                let args = arrange(values.last().unwrap(), args, named)?;
+                tasks.push(Task::Call(args.len()));
+                values.extend(args);
+            }
+            Task::ApplyList => {
+                let args = elements(values.pop().unwrap(), "apply-list")?;
+                tasks.push(Task::Call(args.len()));
+                values.extend(args);
+            }
+            Task::List(len) => {
+                let items = values.split_off(values.len() - len);
+                values.push(list(items));
+            }
+            Task::Map => {
+                let items = elements(values.pop().unwrap(), "map")?;
+                let func = values.pop().unwrap();
+                tasks.push(Task::List(items.len()));
+                for item in items.into_iter().rev() {
+                    tasks.push(Task::Call(1));
+                    tasks.push(Task::Value(item));
+                    tasks.push(Task::Value(func.clone()));
+                }
+            }
+            Task::Fold => {
+                let items = elements(values.pop().unwrap(), "fold")?;
+                let init = values.pop().unwrap();
+                let func = values.pop().unwrap();
+                values.push(init);
+                tasks.push(Task::FoldStep {
+                    func,
+                    rest: items.into_iter(),
+                });
+            }
+            Task::FoldStep { func, mut rest } => {
+                if let Some(item) = rest.next() {
+                    let acc = values.pop().unwrap();
+                    values.extend([func.clone(), acc, item]);
+                    tasks.push(Task::FoldStep { func, rest });
+                    tasks.push(Task::Call(2));
+                }
+            }
+            Task::Value(obj) => values.push(obj),
+        }
+    }
+    Ok(values.pop().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast, eval};
+
+    #[test]
+    fn test_eval_iter() {
+        let programs = vec![
+            ast!((+ (* 2 3) (- 10 (/ 9 3)))),
+            ast!((% 17 5)),
+            ast!((+ 1 2.5)),
+            ast!((If (and (< 1 2) (not (>= 1 2))) 10 20)),
+            ast!((or false (> 3 4))),
+            ast!((neg (- 2 5))),
+            ast!((neg 1.5)),
+            ast!((neg "a")),
+            ast!((<= "a" "b")),
+            ast!((< 1 2.5)),
+            ast!((min (max 1 2) 3)),
+            ast!((max "a" 1)),
+            ast!((and false (/ 1 0))),
+            ast!((Cond ((== 1 2) 10) ((<= 1 1) 20) 30)),
+            ast!((Cond ((== 1 2) 10) 30)),
+            ast!((Case (+ 1 1) (1 10) ((* 1 2) 20) 30)),
+            ast!((Case 5 (1 10) 30)),
+            ast!((Case 1 30)),
+            ast!((Case 1 (1 10) ((/ 1 0) 20) 30)),
+            ast!((Let x 5 (Begin (Define y (+ x 1)) (* x y)))),
+            ast!((Let (a b) (list 1 2) (+ a b))),
+            ast!((Let (a b) (list 1) a)),
+            ast!((LetRec ((f (Func (n) (If (== n 0) 0 (Apply g n)))) (g (Func (n) (Apply f (- n 1))))) (Apply f 3))),
+            ast!((LetRec ((a b) (b 1)) (cons a (cons b nil)))),
+            ast!((Apply (Func (a b) (Define c (+ a b)) (* c c)) 1 2)),
+            ast!((Apply (Apply (Func (n) (Func (x) (+ x n))) 5) 10)),
+            ast!((Begin (Define x 1) (Set! x (+ x 1)) x)),
+            ast!((Begin (Define i 0) (While (< i 5) (Set! i (+ i 1))) i)),
+            ast!((While 1.5 1)),
+            ast!((Begin (Define l nil) (DoTimes (i 3) (Set! l (cons i l))))),
+            ast!((DoTimes (i 0) 1)),
+            ast!((DoTimes (i "3") 1)),
+            ast!((Print (+ 1 2))),
+            ast!((Assert (< 1 2) (/ 1 0))),
+            ast!((Assert (> 1 2) "1 > 2")),
+            ast!((Assert nil (cons 1 2))),
+            ast!((== "a" "a")),
+            ast!((== 'a' 'b')),
+            ast!((!= 'a' 'b')),
+            ast!((!= (list 1) (quote (1)))),
+            ast!((TypeOf (Func () 1))),
+            ast!((If nil 1 2)),
+            ast!((Begin (Define x 1) (If true (Begin (Define x 2) x) 0) x)),
+            ast!((Begin (If true (Define tmp 1) 0) tmp)),
+            ast!((Begin (Cond (true (Define tmp 1)) 0) tmp)),
+            ast!((Begin (Cond (false 0) (Define tmp 1)) tmp)),
+            ast!((Begin (Case 1 (1 (Define tmp 1)) 0) tmp)),
+            ast!((Begin (Case 2 (1 0) (Define tmp 1)) tmp)),
+            ast!((Begin (Define x 1) (Case x (1 (Begin (Define x 2) x)) 0))),
+            ast!((Define x 1)),
+            AST::Begin(vec![]),
+            ast!((cdr (cons 1 (cons (car (cons 2 nil)) nil)))),
+            ast!((car nil)),
+            ast!((cons 1 (cons 2 3))),
+            ast!((cdr (cons 1 2))),
+            ast!((length (cons 1 (list 2)))),
+            ast!((length "ab")),
+            ast!((length 1.5)),
+            ast!((nth (list 10 20 30) 1)),
+            ast!((nth (list 10) 1)),
+            ast!((nth nil {-1})),
+            ast!((quote (1 (2.5 "a") ()))),
+            ast!((quote (1 x))),
+            AST::List(vec![ast!((+ 1 2)), ast!(true)]),
+            ast!((map (Func (x) (* x x)) (list 1 2 3))),
+            ast!((map (Func (x) x) nil)),
+            ast!((map (Func (x) x) 1)),
+            ast!((map 1 (list 1))),
+            ast!((fold (Func (acc x) (- acc x)) 10 (list 1 2 3))),
+            ast!((fold (Func (acc x) (cons x acc)) nil (quote (1 2)))),
+            ast!((fold (Func (acc x) (/ acc 0)) 1 nil)),
+            ast!((fold (Func (acc x) x) 0 "a")),
+            ast!((/ 1 0)),
+            ast!((Set! undefined 1)),
+            ast!((+ 1 undefined)),
+            ast!((Apply 1 2)),
+            ast!((Apply (Func (a) a))),
+            ast!((Apply (Func (a . rest) (cons a rest)) 1 2 3)),
+            ast!((Apply (Func (a b . rest) rest) 1)),
+            ast!((apply-list (Func (a b) (- a b)) (list 5 3))),
+            ast!((Apply (Func (a b) (- a b)) b: 3 a: 5)),
+            ast!((Apply (Func (a b . c) (list a b c)) 1 b: (+ 1 1))),
+            ast!((Apply (Func (a b) a) b: 1 c: 2)),
+            ast!((Apply (Func (a b) a) 1 a: 2)),
+            ast!((apply-list (Func (. rest) rest) nil)),
+            ast!((apply-list (Func (a) a) 1)),
+            ast!((If (Func () 1) 1 2)),
+        ];
+        for program in programs {
+            assert_eq!(
+                eval_iter(program.clone(), &Env::new()),
+                eval(program, &Env::new())
+            );
+        }
+
+        let env = Env::new();
+        let sum = ast!(
+        (Define sum
+            (Func (n)
+                (If (== n 1)
+                    1
+                    (+ n (Apply sum (- n 1)))))));
+        eval_iter(sum, &env).unwrap();
+        // 再帰しても Rust のスタックは伸びない
+        assert_eq!(
+            eval_iter(ast!((Apply sum 10000)), &env).unwrap(),
+            Object::Num(50005000)
+        );
+    }
+
+    #[test]
+    fn test_eval_iter_deep() {
+        let depth = 100_000;
+        let mut ast = AST::Num(0);
+        for _ in 0..depth {
+            ast = AST::Add(Box::new(ast), Box::new(AST::Num(1)));
+        }
+        assert_eq!(eval_iter(ast, &Env::new()).unwrap(), Object::Num(depth));
+    }
+}
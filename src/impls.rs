@@ -1,39 +1,708 @@
-use crate::{Object, AST};
+use std::cmp::Ordering;
 
+use crate::lexer::{Pos, Token};
+use crate::parser::ParseError;
+use crate::{EvalError, Object, AST};
+
+// どちらかが Float なら両方を f64 として扱う
+// 両方 Num のときや数値でないものが混ざっているときは None
+fn promote(left: &Object, right: &Object) -> Option<(f64, f64)> {
+    match (left, right) {
+        (Object::Float(l), Object::Float(r)) => Some((*l, *r)),
+        (Object::Num(l), Object::Float(r)) => Some((*l as f64, *r)),
+        (Object::Float(l), Object::Num(r)) => Some((*l, *r as f64)),
+        _ => None,
+    }
+}
+
+// Num どうしの計算は i64 に収まらなければ Overflow にする
 impl std::ops::Add for Object {
-    type Output = Object;
+    type Output = Result<Object, EvalError>;
     fn add(self, rhs: Self) -> Self::Output {
         match (&self, &rhs) {
-            (Object::Num(left), Object::Num(right)) => Object::Num(left + right),
-            _ => panic!(
-                "left and right are expected to be Num, but got left: {:?}, right: {:?}",
+            (Object::Num(left), Object::Num(right)) => left
+                .checked_add(*right)
+                .map(Object::Num)
+                .ok_or(EvalError::Overflow),
+            (Object::Str(left), Object::Str(right)) => Ok(Object::Str(format!("{}{}", left, right))),
+            (Object::Str(_), _) | (_, Object::Str(_)) => Err(EvalError::TypeMismatch(format!(
+                "left and right are expected to be both Num or both Str, but got left: {:?}, right: {:?}",
                 self, rhs
-            ),
+            ))),
+            _ => match promote(&self, &rhs) {
+                Some((left, right)) => Ok(Object::Float(left + right)),
+                None => Err(EvalError::TypeMismatch(format!(
+                    "left and right are expected to be Num, but got left: {:?}, right: {:?}",
+                    self, rhs
+                ))),
+            },
         }
     }
 }
 
 impl std::ops::Sub for Object {
-    type Output = Object;
+    type Output = Result<Object, EvalError>;
     fn sub(self, rhs: Self) -> Self::Output {
         match (&self, &rhs) {
-            (Object::Num(left), Object::Num(right)) => Object::Num(left - right),
-            _ => panic!(
-                "left and right are expected to be Num, but got left: {:?}, right: {:?}",
-                self, rhs
+            (Object::Num(left), Object::Num(right)) => left
+                .checked_sub(*right)
+                .map(Object::Num)
+                .ok_or(EvalError::Overflow),
+            _ => match promote(&self, &rhs) {
+                Some((left, right)) => Ok(Object::Float(left - right)),
+                None => Err(EvalError::TypeMismatch(format!(
+                    "left and right are expected to be Num, but got left: {:?}, right: {:?}",
+                    self, rhs
+                ))),
+            },
+        }
+    }
+}
+
+impl std::ops::Mul for Object {
+    type Output = Result<Object, EvalError>;
+    fn mul(self, rhs: Self) -> Self::Output {
+        match (&self, &rhs) {
+            (Object::Num(left), Object::Num(right)) => left
+                .checked_mul(*right)
+                .map(Object::Num)
+                .ok_or(EvalError::Overflow),
+            _ => match promote(&self, &rhs) {
+                Some((left, right)) => Ok(Object::Float(left * right)),
+                None => Err(EvalError::TypeMismatch(format!(
+                    "left and right are expected to be Num, but got left: {:?}, right: {:?}",
+                    self, rhs
+                ))),
+            },
+        }
+    }
+}
+
+impl std::ops::Div for Object {
+    type Output = Result<Object, EvalError>;
+    fn div(self, rhs: Self) -> Self::Output {
+        match (&self, &rhs) {
+            (Object::Num(_), Object::Num(0)) => Err(EvalError::DivByZero),
+            (Object::Num(left), Object::Num(right)) => left
+                .checked_div(*right)
+                .map(Object::Num)
+                .ok_or(EvalError::Overflow),
+            _ => match promote(&self, &rhs) {
+                Some((_, 0.0)) => Err(EvalError::DivByZero),
+                Some((left, right)) => Ok(Object::Float(left / right)),
+                None => Err(EvalError::TypeMismatch(format!(
+                    "left and right are expected to be Num, but got left: {:?}, right: {:?}",
+                    self, rhs
+                ))),
+            },
+        }
+    }
+}
+
+impl std::ops::Rem for Object {
+    type Output = Result<Object, EvalError>;
+    fn rem(self, rhs: Self) -> Self::Output {
+        match (&self, &rhs) {
+            (Object::Num(_), Object::Num(0)) => Err(EvalError::DivByZero),
+            (Object::Num(left), Object::Num(right)) => left
+                .checked_rem(*right)
+                .map(Object::Num)
+                .ok_or(EvalError::Overflow),
+            _ => match promote(&self, &rhs) {
+                Some((_, 0.0)) => Err(EvalError::DivByZero),
+                Some((left, right)) => Ok(Object::Float(left % right)),
+                None => Err(EvalError::TypeMismatch(format!(
+                    "left and right are expected to be Num, but got left: {:?}, right: {:?}",
+                    self, rhs
+                ))),
+            },
+        }
+    }
+}
+
+impl std::ops::Neg for Object {
+    type Output = Result<Object, EvalError>;
+    fn neg(self) -> Self::Output {
+        match self {
+            Object::Num(v) => v.checked_neg().map(Object::Num).ok_or(EvalError::Overflow),
+            Object::Float(v) => Ok(Object::Float(-v)),
+            obj => Err(EvalError::TypeMismatch(format!(
+                "neg is expected to take Num or Float, but got {:?}",
+                obj
+            ))),
+        }
+    }
+}
+
+// ループの中で数値の変数をその場で書き換えるためのもの
+// 数値でないものやあふれは評価のエラーではなく使い方の誤りなので panic する
+impl std::ops::AddAssign for Object {
+    fn add_assign(&mut self, rhs: Self) {
+        match (&mut *self, rhs) {
+            (Object::Num(left), Object::Num(right)) => {
+                *left = left
+                    .checked_add(right)
+                    .expect("attempt to add with overflow")
+            }
+            (left, right) => match promote(left, &right) {
+                Some((l, r)) => *left = Object::Float(l + r),
+                None => panic!("cannot add {:?} to {:?}", right, left),
+            },
+        }
+    }
+}
+
+impl std::ops::SubAssign for Object {
+    fn sub_assign(&mut self, rhs: Self) {
+        match (&mut *self, rhs) {
+            (Object::Num(left), Object::Num(right)) => {
+                *left = left
+                    .checked_sub(right)
+                    .expect("attempt to subtract with overflow")
+            }
+            (left, right) => match promote(left, &right) {
+                Some((l, r)) => *left = Object::Float(l - r),
+                None => panic!("cannot subtract {:?} from {:?}", right, left),
+            },
+        }
+    }
+}
+
+// Float どうしの全順序
+// == と合わせて NaN どうしと 0.0 と -0.0 は等しくし、NaN は符号によらずどの数より大きくする
+fn cmp_float(left: f64, right: f64) -> Ordering {
+    match (left.is_nan(), right.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ if left == right => Ordering::Equal,
+        _ => left.total_cmp(&right),
+    }
+}
+
+// Num と Float を丸めずに比べる
+// i64 を f64 にすると 2^53 を超える数は丸められてしまうので、Float の整数部分を i64 にして比べる
+// Num と Float は == で等しくならないので、値が同じなら Num を先にする
+fn cmp_num_float(n: i64, f: f64) -> Ordering {
+    // 2^63 は f64 で正確に表せる
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    if f.is_nan() || f >= LIMIT {
+        return Ordering::Less;
+    }
+    if f < -LIMIT {
+        return Ordering::Greater;
+    }
+    // -2^63 <= trunc < 2^63 なので i64 に収まる
+    // 整数部分が同じなら、f が負の小数部分を持つときだけ n の方が大きい
+    let trunc = f.trunc();
+    n.cmp(&(trunc as i64)).then(if f < trunc {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    })
+}
+
+// 数値どうしと、同じ種類の値どうしを比べる
+// Num と Float は NaN も含めてどれどうしでも比べられ、<、min、max、sort はこの順序を使う
+// 大小のない Nil やリストなども、== で等しいものどうしは Equal にする
+impl PartialOrd for Object {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Object::Num(left), Object::Num(right)) => left.partial_cmp(right),
+            (Object::Float(left), Object::Float(right)) => Some(cmp_float(*left, *right)),
+            (Object::Num(left), Object::Float(right)) => Some(cmp_num_float(*left, *right)),
+            (Object::Float(left), Object::Num(right)) => {
+                Some(cmp_num_float(*right, *left).reverse())
+            }
+            (Object::Bool(left), Object::Bool(right)) => left.partial_cmp(right),
+            (Object::Str(left), Object::Str(right)) => left.partial_cmp(right),
+            (Object::Char(left), Object::Char(right)) => left.partial_cmp(right),
+            _ if self == other => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+// HashMap のキーに使えるように、Float も自分自身と等しくなるように比べる
+// NaN どうしは等しく、0.0 と -0.0 も f64 と同じく等しい
+// Native は関数ポインタのアドレスで比べる
+#[allow(unpredictable_function_pointer_comparisons)]
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Num(l), Object::Num(r)) => l == r,
+            (Object::Float(l), Object::Float(r)) => l == r || (l.is_nan() && r.is_nan()),
+            (Object::Bool(l), Object::Bool(r)) => l == r,
+            (Object::Str(l), Object::Str(r)) => l == r,
+            (Object::Char(l), Object::Char(r)) => l == r,
+            (Object::Nil, Object::Nil) => true,
+            (Object::List(l), Object::List(r)) => l == r,
+            (Object::Pair(l_head, l_tail), Object::Pair(r_head, r_tail)) => {
+                l_head == r_head && l_tail == r_tail
+            }
+            (
+                Object::Function {
+                    params: l_params,
+                    rest: l_rest,
+                    body: l_body,
+                    env: l_env,
+                },
+                Object::Function {
+                    params: r_params,
+                    rest: r_rest,
+                    body: r_body,
+                    env: r_env,
+                },
+            ) => l_params == r_params && l_rest == r_rest && l_body == r_body && l_env == r_env,
+            (
+                Object::Native {
+                    name: l_name,
+                    func: l_func,
+                },
+                Object::Native {
+                    name: r_name,
+                    func: r_func,
+                },
+            ) => l_name == r_name && l_func == r_func,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Object {}
+
+// a == b なら同じハッシュ値になるようにする
+// 関数は中身を比べると環境をたどることになるので、種類だけをハッシュする
+impl std::hash::Hash for Object {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Object::Num(v) => v.hash(state),
+            // 0.0 == -0.0 や NaN どうしは等しいのでビット列をそろえる
+            Object::Float(v) if *v == 0.0 => 0.0f64.to_bits().hash(state),
+            Object::Float(v) if v.is_nan() => f64::NAN.to_bits().hash(state),
+            Object::Float(v) => v.to_bits().hash(state),
+            Object::Bool(b) => b.hash(state),
+            Object::Str(s) => s.hash(state),
+            Object::Char(c) => c.hash(state),
+            Object::List(items) => items.hash(state),
+            Object::Pair(head, tail) => {
+                head.hash(state);
+                tail.hash(state);
+            }
+            Object::Native { name, .. } => name.hash(state),
+            Object::Nil | Object::Function { .. } => {}
+        }
+    }
+}
+
+impl std::fmt::Display for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Object::Num(v) => write!(f, "{}", v),
+            // 1.0 が 1 と表示されて Num と区別できなくならないように Debug の書式を使う
+            Object::Float(v) => write!(f, "{:?}", v),
+            Object::Bool(b) => write!(f, "{}", b),
+            Object::Str(s) => write!(f, "{}", s),
+            Object::Char(c) => write!(f, "{}", c),
+            Object::Nil => write!(f, "nil"),
+            Object::List(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+            // cdr をたどって (1 2 . 3) のように表示する
+            Object::Pair(head, tail) => {
+                write!(f, "({}", head)?;
+                let mut tail = tail.as_ref();
+                while let Object::Pair(head, next) = tail {
+                    write!(f, " {}", head)?;
+                    tail = next;
+                }
+                match tail {
+                    Object::List(items) => {
+                        items.iter().try_for_each(|item| write!(f, " {}", item))?
+                    }
+                    Object::Nil => {}
+                    tail => write!(f, " . {}", tail)?,
+                }
+                write!(f, ")")
+            }
+            Object::Function { params, .. } => write!(f, "#<function/{}>", params.len()),
+            Object::Native { name, .. } => write!(f, "#<native {}>", name),
+        }
+    }
+}
+
+// 式を空白区切りで並べる
+fn write_all(f: &mut std::fmt::Formatter<'_>, asts: &[AST]) -> std::fmt::Result {
+    for ast in asts {
+        write!(f, " {}", ast)?;
+    }
+    Ok(())
+}
+
+// quote の中身は List を括弧だけで表示する
+fn write_datum(f: &mut std::fmt::Formatter<'_>, ast: &AST) -> std::fmt::Result {
+    match ast {
+        AST::List(items) => {
+            write!(f, "(")?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " ")?;
+                }
+                write_datum(f, item)?;
+            }
+            write!(f, ")")
+        }
+        ast => write!(f, "{}", ast),
+    }
+}
+
+// ast! マクロや parser で読める形で表示する
+// 文字列リテラルを lexer が読み戻せる形で書く
+// lexer が解釈するエスケープは \n と \t だけなので、\r や制御文字はそのまま書く
+fn write_str(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl std::fmt::Display for AST {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AST::Num(v) => write!(f, "{}", v),
+            AST::Float(v) => write!(f, "{:?}", v),
+            AST::Bool(b) => write!(f, "{}", b),
+            AST::Str(s) => write_str(f, s),
+            AST::Char(c) => write!(f, "#\\{}", c),
+            AST::Nil => write!(f, "nil"),
+            AST::Ident(id) => write!(f, "{}", id),
+            AST::Add(left, right) => write!(f, "(+ {} {})", left, right),
+            AST::Minus(left, right) => write!(f, "(- {} {})", left, right),
+            AST::Mul(left, right) => write!(f, "(* {} {})", left, right),
+            AST::Div(left, right) => write!(f, "(/ {} {})", left, right),
+            AST::Mod(left, right) => write!(f, "(% {} {})", left, right),
+            AST::Equal(left, right) => write!(f, "(== {} {})", left, right),
+            AST::NotEqual(left, right) => write!(f, "(!= {} {})", left, right),
+            AST::And(left, right) => write!(f, "(and {} {})", left, right),
+            AST::Or(left, right) => write!(f, "(or {} {})", left, right),
+            AST::Not(value) => write!(f, "(not {})", value),
+            AST::Negate(value) => write!(f, "(neg {})", value),
+            AST::Cons(head, tail) => write!(f, "(cons {} {})", head, tail),
+            AST::Car(value) => write!(f, "(car {})", value),
+            AST::Cdr(value) => write!(f, "(cdr {})", value),
+            AST::Length(value) => write!(f, "(length {})", value),
+            AST::Nth(value, index) => write!(f, "(nth {} {})", value, index),
+            AST::Less(left, right) => write!(f, "(< {} {})", left, right),
+            AST::Greater(left, right) => write!(f, "(> {} {})", left, right),
+            AST::LessEq(left, right) => write!(f, "(<= {} {})", left, right),
+            AST::GreaterEq(left, right) => write!(f, "(>= {} {})", left, right),
+            AST::Min(left, right) => write!(f, "(min {} {})", left, right),
+            AST::Max(left, right) => write!(f, "(max {} {})", left, right),
+            AST::If { cond, then, els } => write!(f, "(If {} {} {})", cond, then, els),
+            AST::Cond { clauses, default } => {
+                write!(f, "(Cond")?;
+                for (cond, body) in clauses {
+                    write!(f, " ({} {})", cond, body)?;
+                }
+                write!(f, " {})", default)
+            }
+            AST::Case {
+                scrutinee,
+                arms,
+                default,
+            } => {
+                write!(f, "(Case {}", scrutinee)?;
+                for (key, body) in arms {
+                    write!(f, " ({} {})", key, body)?;
+                }
+                write!(f, " {})", default)
+            }
+            AST::While { cond, body } => write!(f, "(While {} {})", cond, body),
+            AST::Define { name, value } => write!(f, "(Define {} {})", name, value),
+            AST::Set { name, value } => write!(f, "(Set! {} {})", name, value),
+            AST::Begin(exprs) => {
+                write!(f, "(Begin")?;
+                write_all(f, exprs)?;
+                write!(f, ")")
+            }
+            AST::Let { name, value, body } => write!(f, "(Let {} {} {})", name, value, body),
+            AST::LetList { names, value, body } => {
+                write!(f, "(Let ({}) {} {})", names.join(" "), value, body)
+            }
+            AST::LetRec { bindings, body } => {
+                write!(f, "(LetRec (")?;
+                for (i, (name, value)) in bindings.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "({} {})", name, value)?;
+                }
+                write!(f, ") {})", body)
+            }
+            AST::Function { params, rest, body } => {
+                let mut names = params.clone();
+                if let Some(rest) = rest {
+                    names.push(".".to_string());
+                    names.push(rest.clone());
+                }
+                write!(f, "(Func ({})", names.join(" "))?;
+                write_all(f, body)?;
+                write!(f, ")")
+            }
+            AST::Print(value) => write!(f, "(Print {})", value),
+            AST::TypeOf(value) => write!(f, "(TypeOf {})", value),
+            AST::Assert { cond, msg } => write!(f, "(Assert {} {})", cond, msg),
+            AST::List(items) => {
+                write!(f, "(list")?;
+                write_all(f, items)?;
+                write!(f, ")")
+            }
+            AST::DoTimes { count, var, body } => {
+                write!(f, "(DoTimes ({} {}) {})", var, count, body)
+            }
+            AST::Map { func, list } => write!(f, "(map {} {})", func, list),
+            AST::Fold { func, init, list } => write!(f, "(fold {} {} {})", func, init, list),
+            AST::Quote(datum) => {
+                write!(f, "(quote ")?;
+                write_datum(f, datum)?;
+                write!(f, ")")
+            }
+            AST::Apply {
+                fn_lit,
+                args,
+                named,
+            } => {
+                write!(f, "(Apply {}", fn_lit)?;
+                write_all(f, args)?;
+                for (name, arg) in named {
+                    write!(f, " {}: {}", name, arg)?;
+                }
+                write!(f, ")")
+            }
+            AST::ApplyList { fn_lit, args } => write!(f, "(apply-list {} {})", fn_lit, args),
+        }
+    }
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::Undefined(id) => write!(f, "undefined identifier: {}", id),
+            EvalError::TypeMismatch(msg) => write!(f, "type mismatch: {}", msg),
+            EvalError::NotApplicable(obj) => write!(f, "not applicable: {}", obj),
+            EvalError::DivByZero => write!(f, "division by zero"),
+            EvalError::EmptyList => write!(f, "empty list"),
+            EvalError::AssertionFailed(msg) => write!(f, "assertion failed: {}", msg),
+            EvalError::UnknownArgument(name) => write!(f, "unknown argument: {}", name),
+            EvalError::DuplicateArgument(name) => write!(f, "duplicate argument: {}", name),
+            EvalError::IndexOutOfBounds { index, len } => write!(
+                f,
+                "index out of bounds: the len is {} but the index is {}",
+                len, index
+            ),
+            EvalError::Overflow => write!(f, "arithmetic overflow"),
+            EvalError::StepLimitExceeded => write!(f, "step limit exceeded"),
+            EvalError::Arity { expected, got } => write!(
+                f,
+                "wrong number of arguments: expected {}, got {}",
+                expected, got
             ),
         }
     }
 }
 
-impl From<usize> for AST {
-    fn from(v: usize) -> Self {
+impl std::error::Error for EvalError {}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseError::UnclosedParen(pos) => write!(f, "unclosed '(' at {}", pos),
+            ParseError::UnexpectedToken(token, pos) => {
+                write!(f, "unexpected '{}' at {}", token, pos)
+            }
+            ParseError::UnknownToken(token, pos) => {
+                write!(f, "unknown token '{}' at {}", token, pos)
+            }
+            ParseError::UnterminatedString(pos) => write!(f, "unterminated string at {}", pos),
+            ParseError::InvalidForm(form, pos) => write!(f, "invalid form: {} at {}", form, pos),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl std::fmt::Display for Pos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// 入力に書かれていたときの形で表示する
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Quote => write!(f, "'"),
+            Token::Num(v) => write!(f, "{}", v),
+            Token::Float(v) => write!(f, "{:?}", v),
+            Token::Bool(b) => write!(f, "{}", b),
+            Token::Str(s) => write_str(f, s),
+            Token::Char(c) => write!(f, "#\\{}", c),
+            Token::Symbol(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<i64> for AST {
+    fn from(v: i64) -> Self {
         AST::Num(v)
     }
 }
 
+impl From<f64> for AST {
+    fn from(v: f64) -> Self {
+        AST::Float(v)
+    }
+}
+
 impl From<bool> for AST {
     fn from(v: bool) -> Self {
         AST::Bool(v)
     }
 }
+
+impl From<String> for AST {
+    fn from(v: String) -> Self {
+        AST::Str(v)
+    }
+}
+
+impl From<char> for AST {
+    fn from(v: char) -> Self {
+        AST::Char(v)
+    }
+}
+
+impl From<&str> for AST {
+    fn from(v: &str) -> Self {
+        AST::Str(v.to_string())
+    }
+}
+
+// 評価結果を Rust の値として取り出す
+impl TryFrom<Object> for i64 {
+    type Error = EvalError;
+    fn try_from(obj: Object) -> Result<Self, Self::Error> {
+        match obj {
+            Object::Num(v) => Ok(v),
+            obj => Err(EvalError::TypeMismatch(format!(
+                "expected Num, but got {:?}",
+                obj
+            ))),
+        }
+    }
+}
+
+impl TryFrom<Object> for f64 {
+    type Error = EvalError;
+    fn try_from(obj: Object) -> Result<Self, Self::Error> {
+        match obj {
+            Object::Float(v) => Ok(v),
+            obj => Err(EvalError::TypeMismatch(format!(
+                "expected Float, but got {:?}",
+                obj
+            ))),
+        }
+    }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = EvalError;
+    fn try_from(obj: Object) -> Result<Self, Self::Error> {
+        match obj {
+            Object::Bool(b) => Ok(b),
+            obj => Err(EvalError::TypeMismatch(format!(
+                "expected Bool, but got {:?}",
+                obj
+            ))),
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = EvalError;
+    fn try_from(obj: Object) -> Result<Self, Self::Error> {
+        match obj {
+            Object::Str(s) => Ok(s),
+            obj => Err(EvalError::TypeMismatch(format!(
+                "expected Str, but got {:?}",
+                obj
+            ))),
+        }
+    }
+}
+
+impl TryFrom<Object> for char {
+    type Error = EvalError;
+    fn try_from(obj: Object) -> Result<Self, Self::Error> {
+        match obj {
+            Object::Char(c) => Ok(c),
+            obj => Err(EvalError::TypeMismatch(format!(
+                "expected Char, but got {:?}",
+                obj
+            ))),
+        }
+    }
+}
+
+// 評価結果を JSON で返せるようにする
+// Nil は空のリストでもあるが null にする
+// 関数と JSON の数値にできない NaN や無限大は変換できない
+#[cfg(feature = "serde")]
+impl TryFrom<Object> for serde_json::Value {
+    type Error = EvalError;
+    fn try_from(obj: Object) -> Result<Self, Self::Error> {
+        let value = match obj {
+            Object::Num(v) => serde_json::Value::from(v),
+            Object::Float(v) => serde_json::Number::from_f64(v)
+                .map(serde_json::Value::Number)
+                .ok_or_else(|| {
+                    EvalError::TypeMismatch(format!("{} cannot be converted to JSON", v))
+                })?,
+            Object::Bool(b) => serde_json::Value::Bool(b),
+            Object::Str(s) => serde_json::Value::String(s),
+            Object::Char(c) => serde_json::Value::String(c.to_string()),
+            Object::Nil => serde_json::Value::Null,
+            Object::List(items) => serde_json::Value::Array(
+                items
+                    .into_iter()
+                    .map(serde_json::Value::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            obj @ (Object::Pair(..) | Object::Function { .. } | Object::Native { .. }) => {
+                return Err(EvalError::TypeMismatch(format!(
+                    "{} cannot be converted to JSON",
+                    obj
+                )))
+            }
+        };
+        Ok(value)
+    }
+}
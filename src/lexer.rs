@@ -0,0 +1,349 @@
+use crate::parser::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    LParen,
+    RParen,
+    // 'x は (quote x) の省略形
+    Quote,
+    Num(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    // #\a のように書く
+    Char(char),
+    Symbol(String),
+}
+
+// 括弧と空白以外の文字のかたまりを一つのトークンにする
+fn atom(word: &str) -> Token {
+    if let Ok(v) = word.parse::<i64>() {
+        return Token::Num(v);
+    }
+    if let Ok(v) = word.parse::<f64>() {
+        // `inf` や `nan` も f64 としては読めてしまうので数字で始まるものだけにする
+        if word
+            .trim_start_matches('-')
+            .starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        {
+            return Token::Float(v);
+        }
+    }
+    if let Some(c) = word.strip_prefix("#\\") {
+        let mut chars = c.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Token::Char(c);
+        }
+    }
+    match word {
+        "true" => Token::Bool(true),
+        "false" => Token::Bool(false),
+        _ => Token::Symbol(word.to_string()),
+    }
+}
+
+// 入力の中の位置
+// 行も列も 1 から数え、列は文字の数で数える
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pos {
+    pub line: usize,
+    pub column: usize,
+}
+
+// 読んだ文字の位置を数えながら一文字ずつ読む
+struct Cursor<'a> {
+    chars: std::str::Chars<'a>,
+    pos: Pos,
+}
+
+impl Cursor<'_> {
+    // 次に読む文字の位置
+    fn pos(&self) -> Pos {
+        self.pos
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else {
+            self.pos.column += 1;
+        }
+        Some(c)
+    }
+}
+
+// 開きの `"` の次から閉じの `"` までを読む
+// 閉じられないまま入力が終わったら、開きの `"` の位置 start をエラーにする
+fn string(chars: &mut Cursor, start: Pos) -> Result<Token, ParseError> {
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(Token::Str(s)),
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some(c) => s.push(c),
+                None => return Err(ParseError::UnterminatedString(start)),
+            },
+            Some(c) => s.push(c),
+            None => return Err(ParseError::UnterminatedString(start)),
+        }
+    }
+}
+
+// `;` から行末まではコメントとして読み飛ばす
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let tokens = tokenize_with_pos(input)?;
+    Ok(tokens.into_iter().map(|(token, _)| token).collect())
+}
+
+// トークンとそのトークンが始まる位置の組にする
+// 構文エラーの位置を示すのに使う
+pub fn tokenize_with_pos(input: &str) -> Result<Vec<(Token, Pos)>, ParseError> {
+    Ok(scan(input)?.0)
+}
+
+// コメントが始まる `;` の位置
+// 文字列や #\; の中の `;` はコメントではないので含まない
+// 文字列が閉じられていなくて読めない入力では空を返す (エラーは parse が返す)
+pub fn comments(input: &str) -> Vec<Pos> {
+    scan(input)
+        .map(|(_, comments)| comments)
+        .unwrap_or_default()
+}
+
+// 位置つきのトークンと、コメントの位置
+type Scanned = (Vec<(Token, Pos)>, Vec<Pos>);
+
+fn scan(input: &str) -> Result<Scanned, ParseError> {
+    let mut tokens = vec![];
+    let mut comments = vec![];
+    let mut word = String::new();
+    let mut word_pos = Pos { line: 1, column: 1 };
+    let mut in_comment = false;
+    let mut chars = Cursor {
+        chars: input.chars(),
+        pos: Pos { line: 1, column: 1 },
+    };
+    loop {
+        let pos = chars.pos();
+        let Some(c) = chars.next() else {
+            break;
+        };
+        if in_comment {
+            in_comment = c != '\n';
+            continue;
+        }
+        // #\ の直後の文字は括弧や空白でも文字として読む
+        if word == "#\\" {
+            word.push(c);
+            continue;
+        }
+        if c == '(' || c == ')' || c == '\'' || c == ';' || c == '"' || c.is_whitespace() {
+            if !word.is_empty() {
+                tokens.push((atom(&word), word_pos));
+                word.clear();
+            }
+            match c {
+                '(' => tokens.push((Token::LParen, pos)),
+                ')' => tokens.push((Token::RParen, pos)),
+                '\'' => tokens.push((Token::Quote, pos)),
+                ';' => {
+                    in_comment = true;
+                    comments.push(pos);
+                }
+                '"' => tokens.push((string(&mut chars, pos)?, pos)),
+                _ => {}
+            }
+        } else {
+            if word.is_empty() {
+                word_pos = pos;
+            }
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        tokens.push((atom(&word), word_pos));
+    }
+    Ok((tokens, comments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("(Define x 1)").unwrap(),
+            vec![
+                Token::LParen,
+                Token::Symbol("Define".to_string()),
+                Token::Symbol("x".to_string()),
+                Token::Num(1),
+                Token::RParen,
+            ]
+        );
+
+        assert_eq!(
+            tokenize("(Apply plus_two 123 -45 1.5 true)").unwrap(),
+            vec![
+                Token::LParen,
+                Token::Symbol("Apply".to_string()),
+                Token::Symbol("plus_two".to_string()),
+                Token::Num(123),
+                Token::Num(-45),
+                Token::Float(1.5),
+                Token::Bool(true),
+                Token::RParen,
+            ]
+        );
+
+        assert_eq!(
+            tokenize("  (<=\n(- x 1)inf)").unwrap(),
+            vec![
+                Token::LParen,
+                Token::Symbol("<=".to_string()),
+                Token::LParen,
+                Token::Symbol("-".to_string()),
+                Token::Symbol("x".to_string()),
+                Token::Num(1),
+                Token::RParen,
+                Token::Symbol("inf".to_string()),
+                Token::RParen,
+            ]
+        );
+
+        assert_eq!(tokenize("   ").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_tokenize_char() {
+        assert_eq!(
+            tokenize(r"(== #\a #\( #\  #\ab)").unwrap(),
+            vec![
+                Token::LParen,
+                Token::Symbol("==".to_string()),
+                Token::Char('a'),
+                Token::Char('('),
+                Token::Char(' '),
+                Token::Symbol(r"#\ab".to_string()),
+                Token::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_quote() {
+        assert_eq!(
+            tokenize("'(1 'a)").unwrap(),
+            vec![
+                Token::Quote,
+                Token::LParen,
+                Token::Num(1),
+                Token::Quote,
+                Token::Symbol("a".to_string()),
+                Token::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_string() {
+        assert_eq!(
+            tokenize(r#"(== "a b" "say \"hi\"\n;")"#).unwrap(),
+            vec![
+                Token::LParen,
+                Token::Symbol("==".to_string()),
+                Token::Str("a b".to_string()),
+                Token::Str("say \"hi\"\n;".to_string()),
+                Token::RParen,
+            ]
+        );
+        assert_eq!(tokenize("\"\"").unwrap(), vec![Token::Str(String::new())]);
+    }
+
+    #[test]
+    fn test_tokenize_unterminated_string() {
+        let pos = |line, column| Pos { line, column };
+        assert_eq!(
+            tokenize("(== \"abc"),
+            Err(ParseError::UnterminatedString(pos(1, 5)))
+        );
+        assert_eq!(
+            tokenize("(f\n  \"a\\\"b)"),
+            Err(ParseError::UnterminatedString(pos(2, 3)))
+        );
+        // 最後の `\\` は次の文字をエスケープするので、閉じの `"` にならない
+        assert_eq!(
+            tokenize("\"abc\\"),
+            Err(ParseError::UnterminatedString(pos(1, 1)))
+        );
+        assert_eq!(
+            tokenize("\"abc\\\""),
+            Err(ParseError::UnterminatedString(pos(1, 1)))
+        );
+    }
+
+    #[test]
+    fn test_tokenize_comment() {
+        assert_eq!(
+            tokenize("(+ 1 2) ; add them").unwrap(),
+            vec![
+                Token::LParen,
+                Token::Symbol("+".to_string()),
+                Token::Num(1),
+                Token::Num(2),
+                Token::RParen,
+            ]
+        );
+
+        assert_eq!(
+            tokenize("(+ 1 ; first\n 2;second\n)").unwrap(),
+            vec![
+                Token::LParen,
+                Token::Symbol("+".to_string()),
+                Token::Num(1),
+                Token::Num(2),
+                Token::RParen,
+            ]
+        );
+
+        assert_eq!(tokenize("; only a comment").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            comments("(+ 1 2) ; add\n\"a;b\" #\\; ;; two\n"),
+            vec![
+                Pos { line: 1, column: 9 },
+                Pos {
+                    line: 2,
+                    column: 11
+                }
+            ]
+        );
+        assert_eq!(comments("(== \";\" #\\;)"), vec![]);
+    }
+
+    #[test]
+    fn test_tokenize_with_pos() {
+        let pos = |line, column| Pos { line, column };
+        assert_eq!(
+            tokenize_with_pos("(+ 12\n  \"a\nb\" x) ; c\n'y").unwrap(),
+            vec![
+                (Token::LParen, pos(1, 1)),
+                (Token::Symbol("+".to_string()), pos(1, 2)),
+                (Token::Num(12), pos(1, 4)),
+                (Token::Str("a\nb".to_string()), pos(2, 3)),
+                (Token::Symbol("x".to_string()), pos(3, 4)),
+                (Token::RParen, pos(3, 5)),
+                (Token::Quote, pos(4, 1)),
+                (Token::Symbol("y".to_string()), pos(4, 2)),
+            ]
+        );
+    }
+}
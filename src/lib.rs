@@ -1,98 +1,960 @@
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 
+mod desugar;
+mod env;
+mod eval_iter;
 mod impls;
+pub mod lexer;
+mod optimize;
+pub mod parser;
+mod prelude;
+mod pretty;
+mod visit;
+
+pub use desugar::desugar;
+pub use env::{Env, EnvSnapshot, Scope};
+pub use eval_iter::eval_iter;
+pub use optimize::fold_constants;
+pub use prelude::prelude;
+pub use pretty::pretty;
+pub use visit::{ast_diff, ast_eq, depth, gensym, map_ast, size};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AST {
-    Num(usize),
+    Num(i64),
+    Float(f64),
     Add(Box<AST>, Box<AST>),
     Minus(Box<AST>, Box<AST>),
+    Mul(Box<AST>, Box<AST>),
+    Div(Box<AST>, Box<AST>),
+    Mod(Box<AST>, Box<AST>),
     Bool(bool),
+    Str(String),
+    Char(char),
+    Nil,
     If {
         cond: Box<AST>,
         then: Box<AST>,
         els: Box<AST>,
     },
+    Cond {
+        clauses: Vec<(AST, AST)>,
+        default: Box<AST>,
+    },
+    // scrutinee を一度だけ評価して、== で等しい key の body を評価する
+    Case {
+        scrutinee: Box<AST>,
+        arms: Vec<(AST, AST)>,
+        default: Box<AST>,
+    },
+    // cond が真のあいだ body を繰り返し評価する
+    While {
+        cond: Box<AST>,
+        body: Box<AST>,
+    },
+    // var に 0 から count - 1 までを順に束縛して body を評価する
+    DoTimes {
+        count: Box<AST>,
+        var: String,
+        body: Box<AST>,
+    },
     Equal(Box<AST>, Box<AST>),
+    NotEqual(Box<AST>, Box<AST>),
+    And(Box<AST>, Box<AST>),
+    Or(Box<AST>, Box<AST>),
+    Not(Box<AST>),
+    // 符号を反転した数
+    Negate(Box<AST>),
+    Cons(Box<AST>, Box<AST>),
+    Car(Box<AST>),
+    Cdr(Box<AST>),
+    // リストの要素の数か文字列の文字の数
+    Length(Box<AST>),
+    // リストの index 番目の要素
+    Nth(Box<AST>, Box<AST>),
+    Less(Box<AST>, Box<AST>),
+    Greater(Box<AST>, Box<AST>),
+    LessEq(Box<AST>, Box<AST>),
+    GreaterEq(Box<AST>, Box<AST>),
+    Min(Box<AST>, Box<AST>),
+    Max(Box<AST>, Box<AST>),
     Define {
         name: String,
         value: Box<AST>,
     },
+    Set {
+        name: String,
+        value: Box<AST>,
+    },
     Ident(String),
+    Begin(Vec<AST>),
+    Let {
+        name: String,
+        value: Box<AST>,
+        body: Box<AST>,
+    },
+    // value のリストの要素を names に順に束縛する
+    LetList {
+        names: Vec<String>,
+        value: Box<AST>,
+        body: Box<AST>,
+    },
+    // 先にすべての名前を束縛してから値を評価するので、互いに参照しあう関数を作れる
+    LetRec {
+        bindings: Vec<(String, AST)>,
+        body: Box<AST>,
+    },
     Function {
         params: Vec<String>,
-        body: Box<AST>,
+        // (Func (a . rest) ...) の rest
+        rest: Option<String>,
+        body: Vec<AST>,
     },
     Apply {
         fn_lit: Box<AST>,
         args: Vec<AST>,
+        // (Apply f 1 b: 2) の b: 2 のように名前で渡す引数
+        // 位置で渡す引数のあとに書く
+        named: Vec<(String, AST)>,
+    },
+    // args を評価したリストの要素を引数にして呼び出す
+    ApplyList {
+        fn_lit: Box<AST>,
+        args: Box<AST>,
+    },
+    Print(Box<AST>),
+    // cond が偽なら msg を評価して AssertionFailed にする
+    Assert {
+        cond: Box<AST>,
+        msg: Box<AST>,
+    },
+    // 値の種類の名前を Str で返す
+    TypeOf(Box<AST>),
+    // 要素をそれぞれ評価してリストにする
+    List(Vec<AST>),
+    // list の要素それぞれに func を適用した結果をリストにする
+    Map {
+        func: Box<AST>,
+        list: Box<AST>,
     },
+    // init から始めて、それまでの値と list の要素を左から順に func に渡す
+    Fold {
+        func: Box<AST>,
+        init: Box<AST>,
+        list: Box<AST>,
+    },
+    // 中身を評価せずにそのままデータとして返す
+    Quote(Box<AST>),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+// Rust で書いた関数
+pub type NativeFn = fn(Vec<Object>) -> Result<Object, EvalError>;
+
+#[derive(Debug, Clone)]
 pub enum Object {
-    Num(usize),
+    Num(i64),
+    Float(f64),
     Bool(bool),
-    Function { params: Vec<String>, body: Box<AST> },
+    Str(String),
+    Char(char),
+    Nil,
+    // 空のリストは作らずに Nil で表す
+    List(Vec<Object>),
+    // (cons 1 2) のように cdr がリストでない組
+    // cdr がリストか Nil なら cons は List を作るので Pair にはならない
+    Pair(Box<Object>, Box<Object>),
+    Function {
+        params: Vec<String>,
+        // 残りの引数をリストにして束縛する名前
+        rest: Option<String>,
+        // 変数から取り出したり呼び出したりするたびに関数が clone されるので、本体は共有する
+        body: Rc<[AST]>,
+        // 関数が定義されたときの環境
+        env: Env,
+    },
+    Native {
+        name: String,
+        func: NativeFn,
+    },
+}
+
+impl Object {
+    // TypeOf が返す種類の名前
+    // Function と Native はどちらも Apply で呼べるので同じ function にする
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Num(_) => "num",
+            Object::Float(_) => "float",
+            Object::Bool(_) => "bool",
+            Object::Str(_) => "str",
+            Object::Char(_) => "char",
+            Object::Nil => "nil",
+            Object::List(_) => "list",
+            Object::Pair(..) => "pair",
+            Object::Function { .. } | Object::Native { .. } => "function",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    Undefined(String),
+    TypeMismatch(String),
+    NotApplicable(Object),
+    DivByZero,
+    Arity { expected: usize, got: usize },
+    EmptyList,
+    // nth に範囲外の index を渡した
+    IndexOutOfBounds { index: i64, len: usize },
+    // Assert の条件が偽だった
+    AssertionFailed(String),
+    // 名前で渡した引数に対応する仮引数がない
+    UnknownArgument(String),
+    // 同じ仮引数に位置と名前の両方、または同じ名前で二回渡した
+    DuplicateArgument(String),
+    Overflow,
+    StepLimitExceeded,
+}
+
+// 比較演算子用に PartialOrd で両辺を比べる
+fn compare(left: Object, right: Object) -> Result<std::cmp::Ordering, EvalError> {
+    left.partial_cmp(&right).ok_or_else(|| {
+        EvalError::TypeMismatch(format!(
+            "left and right are expected to be comparable, but got left: {:?}, right: {:?}",
+            left, right
+        ))
+    })
+}
+
+// 等しいときは左を返す
+fn minimum(left: Object, right: Object) -> Result<Object, EvalError> {
+    if compare(left.clone(), right.clone())?.is_le() {
+        Ok(left)
+    } else {
+        Ok(right)
+    }
+}
+
+fn maximum(left: Object, right: Object) -> Result<Object, EvalError> {
+    if compare(left.clone(), right.clone())?.is_ge() {
+        Ok(left)
+    } else {
+        Ok(right)
+    }
+}
+
+// If や and/or/not の条件として使うときの真偽
+fn truthy(obj: Object) -> Result<bool, EvalError> {
+    match obj {
+        Object::Bool(b) => Ok(b),
+        Object::Num(v) => Ok(v != 0),
+        Object::Nil => Ok(false),
+        obj => Err(EvalError::TypeMismatch(format!(
+            "condition is expected to be Bool or Num, but got {:?}",
+            obj
+        ))),
+    }
+}
+
+// 空なら Nil にしてリストを作る
+fn list(items: Vec<Object>) -> Object {
+    if items.is_empty() {
+        Object::Nil
+    } else {
+        Object::List(items)
+    }
+}
+
+// tail がリストでなければ Pair になる
+fn cons(head: Object, tail: Object) -> Object {
+    match tail {
+        Object::Nil => Object::List(vec![head]),
+        Object::List(mut items) => {
+            items.insert(0, head);
+            Object::List(items)
+        }
+        tail => Object::Pair(Box::new(head), Box::new(tail)),
+    }
+}
+
+fn car(obj: Object) -> Result<Object, EvalError> {
+    match obj {
+        Object::List(mut items) => Ok(items.swap_remove(0)),
+        Object::Pair(head, _) => Ok(*head),
+        Object::Nil => Err(EvalError::EmptyList),
+        obj => Err(EvalError::TypeMismatch(format!(
+            "car is expected to take List or Pair, but got {:?}",
+            obj
+        ))),
+    }
+}
+
+fn cdr(obj: Object) -> Result<Object, EvalError> {
+    match obj {
+        Object::List(mut items) => {
+            items.remove(0);
+            Ok(list(items))
+        }
+        Object::Pair(_, tail) => Ok(*tail),
+        Object::Nil => Err(EvalError::EmptyList),
+        obj => Err(EvalError::TypeMismatch(format!(
+            "cdr is expected to take List or Pair, but got {:?}",
+            obj
+        ))),
+    }
+}
+
+// Str はバイト数ではなく文字の数を返す
+fn length(obj: Object) -> Result<Object, EvalError> {
+    let len = match obj {
+        Object::List(items) => items.len(),
+        Object::Nil => 0,
+        Object::Str(s) => s.chars().count(),
+        obj => {
+            return Err(EvalError::TypeMismatch(format!(
+                "length is expected to take List or Str, but got {:?}",
+                obj
+            )))
+        }
+    };
+    Ok(Object::Num(len as i64))
+}
+
+// 負の index も範囲外として扱う
+fn nth(obj: Object, index: Object) -> Result<Object, EvalError> {
+    let items = elements(obj, "nth")?;
+    let index = match index {
+        Object::Num(v) => v,
+        obj => {
+            return Err(EvalError::TypeMismatch(format!(
+                "index of nth is expected to be Num, but got {:?}",
+                obj
+            )))
+        }
+    };
+    let len = items.len();
+    usize::try_from(index)
+        .ok()
+        .and_then(|i| items.into_iter().nth(i))
+        .ok_or(EvalError::IndexOutOfBounds { index, len })
+}
+
+// Str はそのまま、ほかの値は表示した形をメッセージにする
+fn assertion_failed(msg: Object) -> EvalError {
+    match msg {
+        Object::Str(s) => EvalError::AssertionFailed(s),
+        msg => EvalError::AssertionFailed(msg.to_string()),
+    }
+}
+
+// (Let (a b) value body) で束縛する子のスコープを作る
+// 要素の数と名前の数が違えば Arity にする
+fn destructure(names: &[String], value: Object, env: &Env) -> Result<Env, EvalError> {
+    let items = elements(value, "Let")?;
+    if items.len() != names.len() {
+        return Err(EvalError::Arity {
+            expected: names.len(),
+            got: items.len(),
+        });
+    }
+    let frame = env.child();
+    for (name, item) in names.iter().zip(items) {
+        frame.define(name.clone(), item);
+    }
+    Ok(frame)
+}
+
+// map などが受け取るリストの要素
+// Nil は空のリストとして扱う
+fn elements(obj: Object, form: &str) -> Result<Vec<Object>, EvalError> {
+    match obj {
+        Object::List(items) => Ok(items),
+        Object::Nil => Ok(vec![]),
+        obj => Err(EvalError::TypeMismatch(format!(
+            "{} is expected to take List, but got {:?}",
+            form, obj
+        ))),
+    }
 }
 
-pub fn eval(ast: AST, env: &mut HashMap<String, Object>) -> Object {
+// quote された式をデータに変換する
+// 括弧で囲まれた部分はリストになる
+fn quote(ast: &AST) -> Result<Object, EvalError> {
     let obj = match ast {
-        AST::Num(v) => Object::Num(v),
-        AST::Add(left, right) => {
-            let left_obj = eval(*left, env);
-            let right_obj = eval(*right, env);
-            left_obj + right_obj
-        }
-        AST::Minus(left, right) => {
-            let left_obj = eval(*left, env);
-            let right_obj = eval(*right, env);
-            left_obj - right_obj
-        }
-        AST::Bool(b) => Object::Bool(b),
-        AST::If { cond, then, els } => match eval(*cond, env) {
-            Object::Bool(true) => eval(*then, env),
-            Object::Bool(false) => eval(*els, env),
-            Object::Num(v) if v != 0 => eval(*then, env),
-            Object::Num(_) => eval(*els, env),
-            _ => unimplemented!(),
+        AST::Num(v) => Object::Num(*v),
+        AST::Float(v) => Object::Float(*v),
+        AST::Bool(b) => Object::Bool(*b),
+        AST::Str(s) => Object::Str(s.clone()),
+        AST::Char(c) => Object::Char(*c),
+        AST::Nil => Object::Nil,
+        AST::List(items) => list(items.iter().map(quote).collect::<Result<_, _>>()?),
+        ast => return Err(EvalError::TypeMismatch(format!("{} cannot be quoted", ast))),
+    };
+    Ok(obj)
+}
+
+// 一回の評価の間だけ共有する状態
+#[derive(Default)]
+struct Context {
+    // 残りのステップ数
+    // None なら制限しない
+    fuel: Cell<Option<usize>>,
+    // これまでに評価した式の数
+    steps: Cell<usize>,
+    // 評価した式とその値を評価し終わった順に記録する
+    // None なら記録しない
+    trace: Option<RefCell<Vec<(AST, Object)>>>,
+}
+
+impl Context {
+    fn with_limit(max_steps: usize) -> Self {
+        Context {
+            fuel: Cell::new(Some(max_steps)),
+            ..Default::default()
+        }
+    }
+
+    fn traced() -> Self {
+        Context {
+            trace: Some(RefCell::new(vec![])),
+            ..Default::default()
+        }
+    }
+
+    fn record(&self, ast: &AST, obj: &Object) {
+        if let Some(trace) = &self.trace {
+            trace.borrow_mut().push((ast.clone(), obj.clone()));
+        }
+    }
+
+    // 式を一つ評価するたびに呼ぶ
+    fn step(&self) -> Result<(), EvalError> {
+        self.steps.set(self.steps.get() + 1);
+        match self.fuel.get() {
+            None => Ok(()),
+            Some(0) => Err(EvalError::StepLimitExceeded),
+            Some(fuel) => {
+                self.fuel.set(Some(fuel - 1));
+                Ok(())
+            }
+        }
+    }
+}
+
+// 末尾位置の関数呼び出しはその場で Rust の再帰にせず、呼び出し元の apply のループに返して続きを評価する
+// こうしておくと末尾再帰する関数をどれだけ深く呼んでもスタックが伸びない
+enum Tail {
+    Value(Object),
+    Call(Object, Vec<Object>),
+}
+
+impl Tail {
+    fn finish(self, ctx: &Context) -> Result<Object, EvalError> {
+        match self {
+            Tail::Value(obj) => Ok(obj),
+            Tail::Call(func, args) => apply(func, args, ctx),
+        }
+    }
+}
+
+// 同じ環境で順番に評価して最後の値を返す
+// 最後の式は末尾位置になる
+// 式が一つもなければ Nil になる
+fn eval_sequence(exprs: &[AST], env: &Env, ctx: &Context) -> Result<Tail, EvalError> {
+    let (last, init) = match exprs.split_last() {
+        Some(split) => split,
+        None => return Ok(Tail::Value(Object::Nil)),
+    };
+    for expr in init {
+        eval_in(expr, env, ctx)?;
+    }
+    eval_tail_pos(last, env, ctx)
+}
+
+// 末尾位置の式を評価する
+// trace を取っているときは末尾位置の式も記録したいので、その場で評価しきって記録する
+// そのため trace を取っているあいだは末尾呼び出しでもスタックが伸びる
+fn eval_tail_pos(ast: &AST, env: &Env, ctx: &Context) -> Result<Tail, EvalError> {
+    if ctx.trace.is_some() {
+        return eval_in(ast, env, ctx).map(Tail::Value);
+    }
+    eval_tail(ast, env, ctx)
+}
+
+// 引数を frame に束縛する
+// rest があるときは params より多い分の引数をリストにして rest に束縛する
+fn bind_args(
+    frame: &Env,
+    params: Vec<String>,
+    rest: Option<String>,
+    mut args: Vec<Object>,
+) -> Result<(), EvalError> {
+    if args.len() < params.len() || (rest.is_none() && args.len() > params.len()) {
+        return Err(EvalError::Arity {
+            expected: params.len(),
+            got: args.len(),
+        });
+    }
+    let rest_args = args.split_off(params.len());
+    for (param, arg) in params.into_iter().zip(args) {
+        frame.define(param, arg);
+    }
+    if let Some(rest) = rest {
+        frame.define(rest, list(rest_args));
+    }
+    Ok(())
+}
+
+// 名前で渡した引数を仮引数の位置に並べて、すべて位置で渡したときと同じ引数の列にする
+fn arrange(
+    func: &Object,
+    mut args: Vec<Object>,
+    named: Vec<(String, Object)>,
+) -> Result<Vec<Object>, EvalError> {
+    let Some((first, _)) = named.first() else {
+        return Ok(args);
+    };
+    let params = match func {
+        Object::Function { params, .. } => params,
+        // Rust の関数には仮引数の名前がない
+        Object::Native { .. } => return Err(EvalError::UnknownArgument(first.clone())),
+        obj => return Err(EvalError::NotApplicable(obj.clone())),
+    };
+    let rest_args = args.split_off(args.len().min(params.len()));
+    let mut slots: Vec<Option<Object>> = args.into_iter().map(Some).collect();
+    slots.resize(params.len(), None);
+    for (name, value) in named {
+        let index = params
+            .iter()
+            .position(|param| *param == name)
+            .ok_or_else(|| EvalError::UnknownArgument(name.clone()))?;
+        if slots[index].is_some() {
+            return Err(EvalError::DuplicateArgument(name));
+        }
+        slots[index] = Some(value);
+    }
+    if slots.iter().any(Option::is_none) {
+        return Err(EvalError::Arity {
+            expected: params.len(),
+            got: slots.iter().flatten().count(),
+        });
+    }
+    Ok(slots.into_iter().flatten().chain(rest_args).collect())
+}
+
+fn apply(func: Object, args: Vec<Object>, ctx: &Context) -> Result<Object, EvalError> {
+    let (mut func, mut args) = (func, args);
+    loop {
+        let (params, rest, body, captured) = match func {
+            Object::Function {
+                params,
+                rest,
+                body,
+                env,
+            } => (params, rest, body, env),
+            Object::Native { func, .. } => return func(args),
+            obj => return Err(EvalError::NotApplicable(obj)),
+        };
+        // 定義時のスコープの子に引数を束縛するので、外側の同名の変数より引数が優先される
+        // 定義時のスコープは共有されているので、後から Define された自分自身も呼び出せる
+        let frame = captured.child();
+        bind_args(&frame, params, rest, args)?;
+        match eval_sequence(&body, &frame, ctx)? {
+            Tail::Value(obj) => return Ok(obj),
+            Tail::Call(next_func, next_args) => {
+                func = next_func;
+                args = next_args;
+            }
+        }
+    }
+}
+
+// Rust の関数を risp から Apply で呼べるように env に束縛する
+pub fn register_builtin(env: &Env, name: &str, func: NativeFn) {
+    env.define(
+        name.to_string(),
+        Object::Native {
+            name: name.to_string(),
+            func,
         },
-        AST::Equal(left, right) => Object::Bool(eval(*left, env) == eval(*right, env)),
-        AST::Define { name, value } => {
-            let value = eval(*value, env);
-            env.insert(name, value.clone());
-            value
+    );
+}
+
+// Rust から risp の関数を呼び出す
+// 関数の本体は定義されたときの環境で評価されるので、呼び出す側の環境は受け取らない
+// 名前つきの引数は渡せないので、位置で渡す
+pub fn apply_function(func: Object, args: Vec<Object>) -> Result<Object, EvalError> {
+    apply(func, args, &Context::default())
+}
+
+pub fn eval(ast: AST, env: &Env) -> Result<Object, EvalError> {
+    eval_ref(&ast, env)
+}
+
+// AST を消費せずに評価するので、同じ AST を何度でも評価できる
+pub fn eval_ref(ast: &AST, env: &Env) -> Result<Object, EvalError> {
+    eval_in(ast, env, &Context::default())
+}
+
+// Env の代わりに Scope を環境として評価する
+// 変数を読むたびに scope から読み、Define や Set! は scope に書き込む
+// 評価中に作った関数も scope を見続けるので、あとから scope を書き換えれば関数からも見え、
+// 関数の中の Set! も scope に届く
+pub fn eval_scope<S: Scope + 'static>(
+    ast: AST,
+    scope: &Rc<RefCell<S>>,
+) -> Result<Object, EvalError> {
+    eval(ast, &Env::with_scope(scope.clone()))
+}
+
+// 同じ AST を環境ごとに一回ずつ評価して、結果を envs と同じ順に返す
+// 一つの環境でエラーになっても残りの環境は評価する
+pub fn eval_many(ast: &AST, envs: &[Env]) -> Vec<Result<Object, EvalError>> {
+    envs.iter().map(|env| eval_ref(ast, env)).collect()
+}
+
+// 式を評価するステップ数の上限を決めて評価する
+// 上限を超えたら StepLimitExceeded になるので、止まらないプログラムも途中で打ち切れる
+pub fn eval_with_limit(ast: AST, env: &Env, max_steps: usize) -> Result<Object, EvalError> {
+    eval_in(&ast, env, &Context::with_limit(max_steps))
+}
+
+// 評価した結果と、そのために評価した式の数を返す
+// 数え方は eval_with_limit のステップ数と同じで、(+ 1 2) なら 1, 2, (+ 1 2) の 3 になる
+pub fn eval_counting(ast: AST, env: &Env) -> Result<(Object, usize), EvalError> {
+    let ctx = Context::default();
+    let obj = eval_in(&ast, env, &ctx)?;
+    Ok((obj, ctx.steps.get()))
+}
+
+// 評価した式とその値を trace に追加しながら評価する
+// (+ 1 2) なら 1, 2, (+ 1 2) の順に記録される
+// If の分岐や関数の本体の最後の式のような末尾位置の式も、外側の式より先に記録される
+// エラーになったときも、それまでに評価し終わった式は trace に残る
+pub fn eval_traced(
+    ast: AST,
+    env: &Env,
+    trace: &mut Vec<(AST, Object)>,
+) -> Result<Object, EvalError> {
+    let ctx = Context::traced();
+    let result = eval_in(&ast, env, &ctx);
+    if let Some(recorded) = ctx.trace {
+        trace.extend(recorded.into_inner());
+    }
+    result
+}
+
+fn eval_in(ast: &AST, env: &Env, ctx: &Context) -> Result<Object, EvalError> {
+    let obj = eval_tail(ast, env, ctx)?.finish(ctx)?;
+    ctx.record(ast, &obj);
+    Ok(obj)
+}
+
+// eval_tail は再帰のたびにスタックに積まれるので、
+// フレームが大きくならないように式ごとの処理をいくつか別の関数にしている
+
+// == は型の変換をせず Object の PartialEq で比べる
+// 種類が違えば常に false なので、(== 1 true) や (== 1 1.0) も false になる
+// 関数は引数と本体が同じで、同じ環境で作られたものどうしが等しい
+fn binary(op: &AST, left: Object, right: Object) -> Result<Object, EvalError> {
+    let obj = match op {
+        AST::Add(..) => (left + right)?,
+        AST::Minus(..) => (left - right)?,
+        AST::Mul(..) => (left * right)?,
+        AST::Div(..) => (left / right)?,
+        AST::Mod(..) => (left % right)?,
+        AST::Equal(..) => Object::Bool(left == right),
+        AST::NotEqual(..) => Object::Bool(left != right),
+        AST::Cons(..) => cons(left, right),
+        AST::Nth(..) => nth(left, right)?,
+        AST::Less(..) => Object::Bool(compare(left, right)?.is_lt()),
+        AST::Greater(..) => Object::Bool(compare(left, right)?.is_gt()),
+        AST::LessEq(..) => Object::Bool(compare(left, right)?.is_le()),
+        AST::GreaterEq(..) => Object::Bool(compare(left, right)?.is_ge()),
+        AST::Min(..) => minimum(left, right)?,
+        AST::Max(..) => maximum(left, right)?,
+        op => unreachable!("{} is not a binary operator", op),
+    };
+    Ok(obj)
+}
+
+fn eval_args(
+    func: &Object,
+    args: &[AST],
+    named: &[(String, AST)],
+    env: &Env,
+    ctx: &Context,
+) -> Result<Vec<Object>, EvalError> {
+    let args = args
+        .iter()
+        .map(|arg| eval_in(arg, env, ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+    let named = named
+        .iter()
+        .map(|(name, arg)| Ok((name.clone(), eval_in(arg, env, ctx)?)))
+        .collect::<Result<Vec<_>, EvalError>>()?;
+    arrange(func, args, named)
+}
+
+fn eval_map(func: &AST, items: &AST, env: &Env, ctx: &Context) -> Result<Object, EvalError> {
+    let func = eval_in(func, env, ctx)?;
+    let items = elements(eval_in(items, env, ctx)?, "map")?;
+    Ok(list(
+        items
+            .into_iter()
+            .map(|item| apply(func.clone(), vec![item], ctx))
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+// msg は条件が偽のときだけ評価する
+fn eval_assert(cond: &AST, msg: &AST, env: &Env, ctx: &Context) -> Result<Object, EvalError> {
+    if truthy(eval_in(cond, env, ctx)?)? {
+        return Ok(Object::Nil);
+    }
+    Err(assertion_failed(eval_in(msg, env, ctx)?))
+}
+
+// 繰り返しごとに新しいスコープを作るので、body で作った関数はそのときの var を覚えている
+fn eval_do_times(
+    count: &AST,
+    var: &str,
+    body: &AST,
+    env: &Env,
+    ctx: &Context,
+) -> Result<Object, EvalError> {
+    let count = i64::try_from(eval_in(count, env, ctx)?)?;
+    let mut last = Object::Nil;
+    for index in 0..count {
+        let frame = env.child();
+        frame.define(var.to_string(), Object::Num(index));
+        last = eval_in(body, &frame, ctx)?;
+    }
+    Ok(last)
+}
+
+fn eval_fold(
+    func: &AST,
+    init: &AST,
+    items: &AST,
+    env: &Env,
+    ctx: &Context,
+) -> Result<Object, EvalError> {
+    let func = eval_in(func, env, ctx)?;
+    let init = eval_in(init, env, ctx)?;
+    let items = elements(eval_in(items, env, ctx)?, "fold")?;
+    items
+        .into_iter()
+        .try_fold(init, |acc, item| apply(func.clone(), vec![acc, item], ctx))
+}
+
+fn eval_tail(ast: &AST, env: &Env, ctx: &Context) -> Result<Tail, EvalError> {
+    ctx.step()?;
+    let obj = match ast {
+        AST::Num(v) => Object::Num(*v),
+        AST::Float(v) => Object::Float(*v),
+        // 両辺を評価してから計算する二項演算はまとめて binary で計算する
+        AST::Add(left, right)
+        | AST::Minus(left, right)
+        | AST::Mul(left, right)
+        | AST::Div(left, right)
+        | AST::Mod(left, right)
+        | AST::Equal(left, right)
+        | AST::NotEqual(left, right)
+        | AST::Cons(left, right)
+        | AST::Nth(left, right)
+        | AST::Less(left, right)
+        | AST::Greater(left, right)
+        | AST::LessEq(left, right)
+        | AST::GreaterEq(left, right)
+        | AST::Min(left, right)
+        | AST::Max(left, right) => {
+            let left_obj = eval_in(left, env, ctx)?;
+            let right_obj = eval_in(right, env, ctx)?;
+            binary(ast, left_obj, right_obj)?
         }
-        AST::Ident(id) => {
-            if let Some(obj) = env.get(&id) {
-                obj.clone()
+        AST::Bool(b) => Object::Bool(*b),
+        AST::Str(s) => Object::Str(s.clone()),
+        AST::Char(c) => Object::Char(*c),
+        AST::Nil => Object::Nil,
+        AST::If { cond, then, els } => {
+            let branch = if truthy(eval_in(cond, env, ctx)?)? {
+                then
             } else {
-                panic!("given ident {} is not defined", id)
-            }
-        }
-        AST::Function { params, body } => Object::Function { params, body },
-        AST::Apply { fn_lit, args } => {
-            let args_val = args.into_iter().map(|arg| eval(arg, &mut env.clone()));
-            let fn_lit_obj = eval(*fn_lit, &mut env.clone());
-            match fn_lit_obj {
-                Object::Function { params, body } => {
-                    let mut deep_env: HashMap<String, Object> =
-                        params.into_iter().zip(args_val).collect();
-                    deep_env.extend(env.clone().into_iter());
-                    eval(*body, &mut deep_env)
+                els
+            };
+            // 分岐の中で Define した名前は If の外に残らない
+            return eval_tail_pos(branch, &env.child(), ctx);
+        }
+        // If と同じく、選んだ節は子のスコープで評価する
+        AST::Cond { clauses, default } => {
+            for (cond, body) in clauses {
+                if truthy(eval_in(cond, env, ctx)?)? {
+                    return eval_tail_pos(body, &env.child(), ctx);
+                }
+            }
+            return eval_tail_pos(default, &env.child(), ctx);
+        }
+        AST::Case {
+            scrutinee,
+            arms,
+            default,
+        } => {
+            let value = eval_in(scrutinee, env, ctx)?;
+            for (key, body) in arms {
+                if eval_in(key, env, ctx)? == value {
+                    return eval_tail_pos(body, &env.child(), ctx);
                 }
-                _ => unimplemented!(),
             }
+            return eval_tail_pos(default, &env.child(), ctx);
+        }
+        AST::While { cond, body } => {
+            while truthy(eval_in(cond, env, ctx)?)? {
+                eval_in(body, env, ctx)?;
+            }
+            Object::Nil
+        }
+        // 左辺だけで結果が決まるときは右辺を評価しない
+        AST::And(left, right) => {
+            Object::Bool(truthy(eval_in(left, env, ctx)?)? && truthy(eval_in(right, env, ctx)?)?)
+        }
+        AST::Or(left, right) => {
+            Object::Bool(truthy(eval_in(left, env, ctx)?)? || truthy(eval_in(right, env, ctx)?)?)
+        }
+        AST::Not(value) => Object::Bool(!truthy(eval_in(value, env, ctx)?)?),
+        AST::Negate(value) => (-eval_in(value, env, ctx)?)?,
+        AST::Car(value) => car(eval_in(value, env, ctx)?)?,
+        AST::Cdr(value) => cdr(eval_in(value, env, ctx)?)?,
+        AST::Length(value) => length(eval_in(value, env, ctx)?)?,
+        AST::TypeOf(value) => Object::Str(eval_in(value, env, ctx)?.type_name().to_string()),
+        AST::List(items) => list(
+            items
+                .iter()
+                .map(|item| eval_in(item, env, ctx))
+                .collect::<Result<_, _>>()?,
+        ),
+        AST::DoTimes { count, var, body } => eval_do_times(count, var, body, env, ctx)?,
+        AST::Assert { cond, msg } => eval_assert(cond, msg, env, ctx)?,
+        AST::Map { func, list } => eval_map(func, list, env, ctx)?,
+        AST::Fold { func, init, list } => eval_fold(func, init, list, env, ctx)?,
+        AST::Quote(datum) => quote(datum)?,
+        AST::Define { name, value } => {
+            let value = eval_in(value, env, ctx)?;
+            env.define(name.clone(), value);
+            Object::Nil
+        }
+        AST::Set { name, value } => {
+            let value = eval_in(value, env, ctx)?;
+            if !env.set(name, value.clone()) {
+                return Err(EvalError::Undefined(name.clone()));
+            }
+            value
+        }
+        AST::Ident(id) => match env.get(id) {
+            Some(obj) => obj,
+            None => return Err(EvalError::Undefined(id.clone())),
+        },
+        AST::Begin(exprs) => return eval_sequence(exprs, env, ctx),
+        // 子のスコープに束縛するので外側の環境には残らない
+        AST::Let { name, value, body } => {
+            let value = eval_in(value, env, ctx)?;
+            let frame = env.child();
+            frame.define(name.clone(), value);
+            return eval_tail_pos(body, &frame, ctx);
+        }
+        AST::LetList { names, value, body } => {
+            let frame = destructure(names, eval_in(value, env, ctx)?, env)?;
+            return eval_tail_pos(body, &frame, ctx);
+        }
+        AST::LetRec { bindings, body } => {
+            let frame = env.child();
+            for (name, _) in bindings {
+                frame.define(name.clone(), Object::Nil);
+            }
+            for (name, value) in bindings {
+                let value = eval_in(value, &frame, ctx)?;
+                frame.define(name.clone(), value);
+            }
+            return eval_tail_pos(body, &frame, ctx);
+        }
+        AST::Function { params, rest, body } => Object::Function {
+            params: params.clone(),
+            rest: rest.clone(),
+            body: body.as_slice().into(),
+            env: env.clone(),
+        },
+        AST::Print(value) => {
+            let value = eval_in(value, env, ctx)?;
+            println!("{}", value);
+            value
+        }
+        AST::Apply {
+            fn_lit,
+            args,
+            named,
+        } => {
+            let fn_lit_obj = eval_in(fn_lit, env, ctx)?;
+            let args_val = eval_args(&fn_lit_obj, args, named, env, ctx)?;
+            return Ok(Tail::Call(fn_lit_obj, args_val));
+        }
+        AST::ApplyList { fn_lit, args } => {
+            let fn_lit_obj = eval_in(fn_lit, env, ctx)?;
+            let args_val = elements(eval_in(args, env, ctx)?, "apply-list")?;
+            return Ok(Tail::Call(fn_lit_obj, args_val));
         }
     };
     // dbg!(obj)
-    obj
+    Ok(Tail::Value(obj))
+}
+
+// parser::parse_program で読んだ式を同じ環境で順番に評価して最後の値を返す
+pub fn eval_program(forms: Vec<AST>, env: &Env) -> Result<Object, EvalError> {
+    let ctx = Context::default();
+    eval_sequence(&forms, env, &ctx)?.finish(&ctx)
 }
 
 // 関数呼び出しは型や引数が一致していないと呼び出せないが
 // マクロは型も引数の個数も一致してなくても呼び出せる
 #[macro_export]
 macro_rules! ast {
+    // Cond の節を一つずつ読んで、最後に残った一つを default にする
+    // `$( ($c:tt $b:tt) )* $default:tt` と書くとどちらにマッチするか決められないのでこうしている
+    (@cond [$( ($cond:tt $body:tt) )*] $default:tt) => {
+        $crate::AST::Cond {
+            clauses: vec![$( (ast!($cond), ast!($body)) ),*],
+            default: Box::new(ast!($default)),
+        }
+    };
+    (@cond [$( $clause:tt )*] $next:tt $( $rest:tt )+) => {
+        ast!(@cond [$( $clause )* $next] $( $rest )+)
+    };
+    // Case の節も Cond と同じように読む
+    (@case $scrutinee:tt [$( ($key:tt $body:tt) )*] $default:tt) => {
+        $crate::AST::Case {
+            scrutinee: Box::new(ast!($scrutinee)),
+            arms: vec![$( (ast!($key), ast!($body)) ),*],
+            default: Box::new(ast!($default)),
+        }
+    };
+    (@case $scrutinee:tt [$( $arm:tt )*] $next:tt $( $rest:tt )+) => {
+        ast!(@case $scrutinee [$( $arm )* $next] $( $rest )+)
+    };
+    // Apply の引数を一つずつ読んで、`name: value` の形のものを名前付きの引数に分ける
+    (@apply $fn_lit:tt [$( $arg:tt )*] [$( ($name:ident $value:tt) )*]) => {
+        $crate::AST::Apply {
+            fn_lit: Box::new(ast!($fn_lit)),
+            args: vec![$( ast!($arg) ), *],
+            named: vec![$( (std::stringify!($name).to_string(), ast!($value)) ),*],
+        }
+    };
+    (@apply $fn_lit:tt [$( $arg:tt )*] [$( $named:tt )*] $name:ident : $value:tt $( $rest:tt )*) => {
+        ast!(@apply $fn_lit [$( $arg )*] [$( $named )* ($name $value)] $( $rest )*)
+    };
+    (@apply $fn_lit:tt [$( $arg:tt )*] [] $next:tt $( $rest:tt )*) => {
+        ast!(@apply $fn_lit [$( $arg )* $next] [] $( $rest )*)
+    };
     // tt には `(+ 1 2)` とか `1` などがマッチする
     ((+ $left:tt $right:tt)) => {
         // このマクロの中でASTやpubにしてるやつを使いたいときは
@@ -102,9 +964,120 @@ macro_rules! ast {
     ((- $left:tt $right:tt)) => {
         $crate::AST::Minus(Box::new(ast!($left)), Box::new(ast!($right)))
     };
+    ((* $left:tt $right:tt)) => {
+        $crate::AST::Mul(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((/ $left:tt $right:tt)) => {
+        $crate::AST::Div(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((% $left:tt $right:tt)) => {
+        $crate::AST::Mod(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
     ((== $left:tt $right:tt)) => {
         $crate::AST::Equal(Box::new(ast!($left)), Box::new(ast!($right)))
     };
+    ((!= $left:tt $right:tt)) => {
+        $crate::AST::NotEqual(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((and $left:tt $right:tt)) => {
+        $crate::AST::And(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((or $left:tt $right:tt)) => {
+        $crate::AST::Or(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((not $value:tt)) => {
+        $crate::AST::Not(Box::new(ast!($value)))
+    };
+    ((neg $value:tt)) => {
+        $crate::AST::Negate(Box::new(ast!($value)))
+    };
+    ((cons $head:tt $tail:tt)) => {
+        $crate::AST::Cons(Box::new(ast!($head)), Box::new(ast!($tail)))
+    };
+    ((car $value:tt)) => {
+        $crate::AST::Car(Box::new(ast!($value)))
+    };
+    ((cdr $value:tt)) => {
+        $crate::AST::Cdr(Box::new(ast!($value)))
+    };
+    // first と rest は car と cdr の別名
+    ((first $value:tt)) => {
+        $crate::AST::Car(Box::new(ast!($value)))
+    };
+    ((rest $value:tt)) => {
+        $crate::AST::Cdr(Box::new(ast!($value)))
+    };
+    ((length $value:tt)) => {
+        $crate::AST::Length(Box::new(ast!($value)))
+    };
+    ((nth $list:tt $index:tt)) => {
+        $crate::AST::Nth(Box::new(ast!($list)), Box::new(ast!($index)))
+    };
+    // prelude の num? や bool->num などを呼ぶ
+    // 名前に `?` や `->` を含むので $name:ident では書けない
+    // list? が (list ...) に読まれないように先に書いておく
+    ((bool->num $value:tt)) => {
+        ast!(@prelude "bool->num" $value)
+    };
+    ((num->bool $value:tt)) => {
+        ast!(@prelude "num->bool" $value)
+    };
+    ((num? $value:tt)) => {
+        ast!(@prelude "num?" $value)
+    };
+    ((bool? $value:tt)) => {
+        ast!(@prelude "bool?" $value)
+    };
+    ((str? $value:tt)) => {
+        ast!(@prelude "str?" $value)
+    };
+    ((func? $value:tt)) => {
+        ast!(@prelude "func?" $value)
+    };
+    ((list? $value:tt)) => {
+        ast!(@prelude "list?" $value)
+    };
+    (@prelude $name:literal $value:tt) => {
+        $crate::AST::Apply {
+            fn_lit: Box::new($crate::AST::Ident($name.to_string())),
+            args: vec![ast!($value)],
+            named: vec![],
+        }
+    };
+    ((list $( $item:tt )*)) => {
+        $crate::AST::List(vec![$( ast!($item) ),*])
+    };
+    ((map $func:tt $list:tt)) => {
+        $crate::AST::Map {
+            func: Box::new(ast!($func)),
+            list: Box::new(ast!($list)),
+        }
+    };
+    ((fold $func:tt $init:tt $list:tt)) => {
+        $crate::AST::Fold {
+            func: Box::new(ast!($func)),
+            init: Box::new(ast!($init)),
+            list: Box::new(ast!($list)),
+        }
+    };
+    ((< $left:tt $right:tt)) => {
+        $crate::AST::Less(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((> $left:tt $right:tt)) => {
+        $crate::AST::Greater(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((<= $left:tt $right:tt)) => {
+        $crate::AST::LessEq(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((>= $left:tt $right:tt)) => {
+        $crate::AST::GreaterEq(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((min $left:tt $right:tt)) => {
+        $crate::AST::Min(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
+    ((max $left:tt $right:tt)) => {
+        $crate::AST::Max(Box::new(ast!($left)), Box::new(ast!($right)))
+    };
     ((If $cond:tt $then:tt $els:tt)) => {
         $crate::AST::If {
             cond: Box::new(ast!($cond)),
@@ -112,22 +1085,107 @@ macro_rules! ast {
             els: Box::new(ast!($els)),
         }
     };
+    ((While $cond:tt $body:tt)) => {
+        $crate::AST::While {
+            cond: Box::new(ast!($cond)),
+            body: Box::new(ast!($body)),
+        }
+    };
+    ((DoTimes ($var:ident $count:tt) $body:tt)) => {
+        $crate::AST::DoTimes {
+            count: Box::new(ast!($count)),
+            var: std::stringify!($var).to_string(),
+            body: Box::new(ast!($body)),
+        }
+    };
     ((Define $name:ident $value:tt)) => {
         $crate::AST::Define {
             name: std::stringify!($name).to_string(),
             value: Box::new(ast!($value)),
         }
     };
-    ((Func ($( $param:ident )*) $body:tt)) => {
-        $crate::AST::Function {
-            params: vec![$( stringify!($param).to_string() ), *],
+    ((Set! $name:ident $value:tt)) => {
+        $crate::AST::Set {
+            name: std::stringify!($name).to_string(),
+            value: Box::new(ast!($value)),
+        }
+    };
+    // quote の中の括弧は式ではなくリストとして読む
+    (@datum ($( $item:tt )*)) => {
+        $crate::AST::List(vec![$( ast!(@datum $item) ),*])
+    };
+    (@datum $atom:tt) => {
+        ast!($atom)
+    };
+    ((Begin $( $expr:tt )+)) => {
+        $crate::AST::Begin(vec![$( ast!($expr) ),*])
+    };
+    ((Cond $( $clause:tt )+)) => {
+        ast!(@cond [] $( $clause )+)
+    };
+    ((Case $scrutinee:tt $( $arm:tt )+)) => {
+        ast!(@case $scrutinee [] $( $arm )+)
+    };
+    ((Let $name:ident $value:tt $body:tt)) => {
+        $crate::AST::Let {
+            name: std::stringify!($name).to_string(),
+            value: Box::new(ast!($value)),
+            body: Box::new(ast!($body)),
+        }
+    };
+    ((Let ($( $name:ident )*) $value:tt $body:tt)) => {
+        $crate::AST::LetList {
+            names: vec![$( std::stringify!($name).to_string() ),*],
+            value: Box::new(ast!($value)),
             body: Box::new(ast!($body)),
         }
     };
+    ((LetRec ($( ($name:ident $value:tt) )*) $body:tt)) => {
+        $crate::AST::LetRec {
+            bindings: vec![$( (std::stringify!($name).to_string(), ast!($value)) ),*],
+            body: Box::new(ast!($body)),
+        }
+    };
+    ((Func ($( $param:ident )* . $rest:ident) $( $body:tt )+)) => {
+        $crate::AST::Function {
+            params: vec![$( stringify!($param).to_string() ), *],
+            rest: Some(stringify!($rest).to_string()),
+            body: vec![$( ast!($body) ),*],
+        }
+    };
+    ((Func ($( $param:ident )*) $( $body:tt )+)) => {
+        $crate::AST::Function {
+            params: vec![$( stringify!($param).to_string() ), *],
+            rest: None,
+            body: vec![$( ast!($body) ),*],
+        }
+    };
+    // (Define f (Func (x) ...)) の省略形
+    ((Defun $name:ident $params:tt $( $body:tt )+)) => {
+        ast!((Define $name (Func $params $( $body )+)))
+    };
+    ((quote $datum:tt)) => {
+        $crate::AST::Quote(Box::new(ast!(@datum $datum)))
+    };
+    ((TypeOf $value:tt)) => {
+        $crate::AST::TypeOf(Box::new(ast!($value)))
+    };
+    ((Print $value:tt)) => {
+        $crate::AST::Print(Box::new(ast!($value)))
+    };
+    ((Assert $cond:tt $msg:tt)) => {
+        $crate::AST::Assert {
+            cond: Box::new(ast!($cond)),
+            msg: Box::new(ast!($msg)),
+        }
+    };
     ((Apply $fn_lit:tt $( $arg:tt )*)) => {
-        $crate::AST::Apply {
+        ast!(@apply $fn_lit [] [] $( $arg )*)
+    };
+    ((apply-list $fn_lit:tt $args:tt)) => {
+        $crate::AST::ApplyList {
             fn_lit: Box::new(ast!($fn_lit)),
-            args: vec![$( ast!($arg) ), *],
+            args: Box::new(ast!($args)),
         }
     };
     // $name:ident にマッチしてしまうので先に書いておく
@@ -137,6 +1195,15 @@ macro_rules! ast {
     (false) => {
         $crate::AST::Bool(false)
     };
+    (nil) => {
+        $crate::AST::Nil
+    };
+    // -5 は `-` と `5` の 2 つのトークンになるので、リテラルの前に `-` があれば負の数にする
+    // 二項演算の `-` は括弧の中にしか書けないので区別できる
+    // 括弧の中に負の数を書くときは `(+ 1 {-5})` のようにブロックで囲む
+    (- $value:literal) => {
+        $crate::AST::from(-$value)
+    };
     ($name:ident) => {
         $crate::AST::Ident(std::stringify!($name).to_string())
     };
@@ -146,96 +1213,2219 @@ macro_rules! ast {
     };
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_eval() {
+        let empty_env = Env::new();
+        let ast = AST::Num(1);
+        assert_eq!(eval(ast, &empty_env).unwrap(), Object::Num(1));
+
+        // (1 + 2)
+        // (+ 1 2)
+        let simple_add = AST::Add(Box::new(AST::Num(1)), Box::new(AST::Num(2)));
+        assert_eq!(eval(simple_add, &empty_env).unwrap(), Object::Num(3));
+
+        // ((((1 + 2) + 3) + 4) + 5)
+        // (+ (+ (+ (+ 1 2) 3) 4 ) 5)
+        let complicated_add = AST::Add(
+            Box::new(AST::Add(
+                Box::new(AST::Add(
+                    Box::new(AST::Add(Box::new(AST::Num(1)), Box::new(AST::Num(2)))),
+                    Box::new(AST::Num(3)),
+                )),
+                Box::new(AST::Num(4)),
+            )),
+            Box::new(AST::Num(5)),
+        );
+
+        assert_eq!(eval(complicated_add, &empty_env).unwrap(), Object::Num(15));
+
+        assert_eq!(
+            eval(
+                // ((1 + 2) - 2)
+                // (- (+ 1 2) 2)
+                ast!((- (+ 1 2) 2)),
+                &empty_env
+            )
+            .unwrap(),
+            Object::Num(1)
+        );
+
+        assert_eq!(eval(ast!((- 2 5)), &empty_env).unwrap(), Object::Num(-3));
+
+        assert_eq!(eval(ast!((* 6 7)), &empty_env).unwrap(), Object::Num(42));
+        assert_eq!(
+            eval(ast!((* (+ 1 2) (- 5 1))), &empty_env).unwrap(),
+            Object::Num(12)
+        );
+
+        assert_eq!(eval(ast!((/ 10 3)), &empty_env).unwrap(), Object::Num(3));
+        assert_eq!(eval(ast!((/ 10 2)), &empty_env).unwrap(), Object::Num(5));
+
+        assert_eq!(eval(ast!((% 17 5)), &empty_env).unwrap(), Object::Num(2));
+        assert_eq!(
+            eval(ast!((== (% 10 2) 0)), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+
+        assert_eq!(eval(ast!(3.5), &empty_env).unwrap(), Object::Float(3.5));
+        assert_eq!(
+            eval(ast!((+ 1.5 2.5)), &empty_env).unwrap(),
+            Object::Float(4.0)
+        );
+        assert_eq!(
+            eval(ast!((- 1.5 2.0)), &empty_env).unwrap(),
+            Object::Float(-0.5)
+        );
+        assert_eq!(
+            eval(ast!((* 1.5 2.0)), &empty_env).unwrap(),
+            Object::Float(3.0)
+        );
+        assert_eq!(
+            eval(ast!((/ 1.0 4.0)), &empty_env).unwrap(),
+            Object::Float(0.25)
+        );
+        assert_eq!(
+            eval(ast!((== 0.5 (/ 1.0 2.0))), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+
+        assert_eq!(
+            eval(ast!((+ 1 2.5)), &empty_env).unwrap(),
+            Object::Float(3.5)
+        );
+        assert_eq!(
+            eval(ast!((+ 2.5 1)), &empty_env).unwrap(),
+            Object::Float(3.5)
+        );
+        assert_eq!(eval(ast!((+ 1 2)), &empty_env).unwrap(), Object::Num(3));
+        assert_eq!(
+            eval(ast!((- 1 0.5)), &empty_env).unwrap(),
+            Object::Float(0.5)
+        );
+        assert_eq!(
+            eval(ast!((* 2 1.5)), &empty_env).unwrap(),
+            Object::Float(3.0)
+        );
+        assert_eq!(
+            eval(ast!((/ 1 4.0)), &empty_env).unwrap(),
+            Object::Float(0.25)
+        );
+
+        assert_eq!(
+            eval(ast!("a"), &empty_env).unwrap(),
+            Object::Str("a".to_string())
+        );
+        assert_eq!(
+            eval(ast!((== "a" "a")), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((== "a" "b")), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(
+            eval(ast!((+ "foo" "bar")), &empty_env).unwrap(),
+            Object::Str("foobar".to_string())
+        );
+
+        assert_eq!(eval(ast!(nil), &empty_env).unwrap(), Object::Nil);
+        assert_eq!(
+            eval(ast!((If nil 1 2)), &empty_env).unwrap(),
+            Object::Num(2)
+        );
+        assert_eq!(
+            eval(ast!((not nil)), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((== nil nil)), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+
+        assert_eq!(eval(ast!(true), &empty_env).unwrap(), Object::Bool(true));
+        assert_eq!(eval(ast!(false), &empty_env).unwrap(), Object::Bool(false));
+
+        assert_eq!(
+            eval(ast!((If true 1 2)), &empty_env).unwrap(),
+            Object::Num(1)
+        );
+        assert_eq!(
+            eval(ast!((If false 1 2)), &empty_env).unwrap(),
+            Object::Num(2)
+        );
+
+        assert_eq!(eval(ast!((If 1 1 2)), &empty_env).unwrap(), Object::Num(1));
+        assert_eq!(eval(ast!((If 0 1 2)), &empty_env).unwrap(), Object::Num(2));
+
+        assert_eq!(
+            eval(ast!((== 3 (+ 1 2))), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((== 0 (+ 1 2))), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+
+        assert_eq!(eval(ast!((< 3 5)), &empty_env).unwrap(), Object::Bool(true));
+        assert_eq!(
+            eval(ast!((< 5 3)), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(
+            eval(ast!((< 5 5)), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+
+        assert_eq!(
+            eval(ast!((and true 1)), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((and true 0)), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(
+            eval(ast!((or false 0)), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(
+            eval(ast!((or 0 true)), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((not false)), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((not 1)), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(eval(ast!((neg 5)), &empty_env).unwrap(), Object::Num(-5));
+        assert_eq!(
+            eval(ast!((neg (- 2 5))), &empty_env).unwrap(),
+            Object::Num(3)
+        );
+        assert_eq!(
+            eval(ast!((+ 10 (neg (* 2 3)))), &empty_env).unwrap(),
+            Object::Num(4)
+        );
+        assert_eq!(
+            eval(ast!((neg 1.5)), &empty_env).unwrap(),
+            Object::Float(-1.5)
+        );
+
+        // 右辺は評価されないので division by zero にならない
+        assert_eq!(
+            eval(ast!((and false (/ 1 0))), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(
+            eval(ast!((or true (/ 1 0))), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+
+        assert_eq!(eval(ast!((> 5 3)), &empty_env).unwrap(), Object::Bool(true));
+        assert_eq!(
+            eval(ast!((> 5 5)), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(
+            eval(ast!((<= 5 5)), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((<= 6 5)), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(
+            eval(ast!((>= 5 5)), &empty_env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((>= 4 5)), &empty_env).unwrap(),
+            Object::Bool(false)
+        );
+    }
+
+    #[test]
+    fn test_object_assign_ops() {
+        let mut n = Object::Num(1);
+        n += Object::Num(2);
+        assert_eq!(n, Object::Num(3));
+        n -= Object::Num(5);
+        assert_eq!(n, Object::Num(-2));
+        n += Object::Float(0.5);
+        assert_eq!(n, Object::Float(-1.5));
+        n -= Object::Num(1);
+        assert_eq!(n, Object::Float(-2.5));
+
+        let mut i = Object::Num(0);
+        for _ in 0..10 {
+            i += Object::Num(1);
+        }
+        assert_eq!(i, Object::Num(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot add")]
+    fn test_object_add_assign_not_num() {
+        let mut s = Object::Str("a".to_string());
+        s += Object::Num(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow")]
+    fn test_object_sub_assign_overflow() {
+        let mut n = Object::Num(i64::MIN);
+        n -= Object::Num(1);
+    }
+
+    #[test]
+    fn test_eval_error() {
+        let env = Env::new();
+        assert_eq!(eval(ast!((/ 1 (- 1 1))), &env), Err(EvalError::DivByZero));
+        assert_eq!(eval(ast!((% 1 0)), &env), Err(EvalError::DivByZero));
+        assert_eq!(eval(ast!((/ 1.0 0)), &env), Err(EvalError::DivByZero));
+
+        assert_eq!(
+            Object::Num(i64::MAX) + Object::Num(1),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            Object::Num(i64::MIN) - Object::Num(1),
+            Err(EvalError::Overflow)
+        );
+        // Num は i64 なので、小さい数から大きい数を引いても負の数になるだけで Overflow にはならない
+        assert_eq!(Object::Num(2) - Object::Num(5), Ok(Object::Num(-3)));
+        assert_eq!(
+            Object::Num(i64::MIN + 1) - Object::Num(1),
+            Ok(Object::Num(i64::MIN))
+        );
+        assert_eq!(
+            Object::Num(i64::MAX) * Object::Num(2),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            Object::Num(i64::MIN) / Object::Num(-1),
+            Err(EvalError::Overflow)
+        );
+        assert_eq!(
+            Object::Num(i64::MIN) % Object::Num(-1),
+            Err(EvalError::Overflow)
+        );
+        let factorial = ast!(
+        (Define fact
+            (Func (n)
+                (If (== n 0)
+                    1
+                    (* n (Apply fact (- n 1)))))));
+        eval(factorial, &env).unwrap();
+        assert_eq!(
+            eval(ast!((Apply fact 20)), &env).unwrap(),
+            Object::Num(2432902008176640000)
+        );
+        assert_eq!(eval(ast!((Apply fact 21)), &env), Err(EvalError::Overflow));
+
+        assert!(matches!(
+            eval(ast!((< 1 true)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval(ast!((+ 1 (== 1 1))), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(
+            eval(ast!((+ "a" 1)), &env),
+            Err(EvalError::TypeMismatch(
+                "left and right are expected to be both Num or both Str, but got left: Str(\"a\"), right: Num(1)"
+                    .to_string()
+            ))
+        );
+        assert!(matches!(
+            eval(ast!((- "a" "b")), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval(ast!((If (Func () 1) 1 2)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+
+        assert_eq!(
+            eval(ast!(nonexistent), &env),
+            Err(EvalError::Undefined("nonexistent".to_string()))
+        );
+        assert_eq!(
+            eval(ast!((Apply (Func (x) (+ x y)) 1)), &env),
+            Err(EvalError::Undefined("y".to_string()))
+        );
+
+        assert_eq!(
+            eval(ast!((Apply (Func (a b) (+ a b)) 1)), &env),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert_eq!(
+            eval(ast!((Apply (Func (a) a) 1 2)), &env),
+            Err(EvalError::Arity {
+                expected: 1,
+                got: 2
+            })
+        );
+
+        assert_eq!(
+            eval(ast!((Apply 1 2)), &env),
+            Err(EvalError::NotApplicable(Object::Num(1)))
+        );
+    }
+
+    #[test]
+    fn test_eval_negate_error() {
+        let env = Env::new();
+        assert!(matches!(
+            eval(ast!((neg true)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval(ast!((neg "a")), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        env.define("min".to_string(), Object::Num(i64::MIN));
+        assert_eq!(eval(ast!((neg min)), &env), Err(EvalError::Overflow));
+    }
+
+    #[test]
+    fn test_object_partial_ord() {
+        use std::cmp::Ordering;
+
+        assert!(Object::Num(1) < Object::Num(2));
+        assert!(Object::Num(-3) >= Object::Num(-3));
+        assert!(Object::Float(2.5) > Object::Float(1.5));
+        assert!(Object::Bool(false) < Object::Bool(true));
+        assert!(Object::Str("a".to_string()) < Object::Str("b".to_string()));
+        assert_eq!(
+            Object::Num(1).partial_cmp(&Object::Num(1)),
+            Some(Ordering::Equal)
+        );
+
+        let func = Object::Function {
+            params: vec![],
+            rest: None,
+            body: Rc::from([ast!(1)]),
+            env: Env::new(),
+        };
+        assert_eq!(func.partial_cmp(&func), Some(Ordering::Equal));
+        assert_eq!(Object::Num(1).partial_cmp(&Object::Bool(true)), None);
+        // Num と Float は == では等しくないので、値が同じなら Num が小さい
+        assert_eq!(
+            Object::Num(1).partial_cmp(&Object::Float(1.0)),
+            Some(Ordering::Less)
+        );
+        assert!(Object::Float(0.5) < Object::Num(1));
+        assert_eq!(Object::Nil.partial_cmp(&Object::Nil), Some(Ordering::Equal));
+        let list = |items: Vec<i64>| Object::List(items.into_iter().map(Object::Num).collect());
+        assert_eq!(
+            list(vec![1, 2]).partial_cmp(&list(vec![1, 2])),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(list(vec![1, 2]).partial_cmp(&list(vec![2])), None);
+        let pair = Object::Pair(Box::new(Object::Num(1)), Box::new(Object::Nil));
+        assert_eq!(pair.partial_cmp(&pair.clone()), Some(Ordering::Equal));
+        assert_eq!(pair.partial_cmp(&Object::Nil), None);
+
+        let env = Env::new();
+        assert_eq!(eval(ast!((< 1.5 2.5)), &env).unwrap(), Object::Bool(true));
+        assert_eq!(eval(ast!((< 1 1.5)), &env).unwrap(), Object::Bool(true));
+        assert_eq!(eval(ast!((>= 2.0 2)), &env).unwrap(), Object::Bool(true));
+        assert_eq!(
+            eval(ast!((>= false true)), &env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(eval(ast!((<= "ab" "b")), &env).unwrap(), Object::Bool(true));
+        assert_eq!(
+            eval(ast!((>= (list 1 2) (list 1 2))), &env).unwrap(),
+            Object::Bool(true)
+        );
+        assert!(matches!(
+            eval(ast!((< (list 1) (list 2))), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_object_partial_ord_numbers() {
+        // 数値はどの二つも比べられ、どの三つを取っても順序が矛盾せず、Equal は == と一致する
+        let values = [
+            Object::Num(i64::MIN),
+            Object::Num(-1),
+            Object::Num(0),
+            Object::Num(1),
+            Object::Num((1 << 53) + 1),
+            Object::Num(i64::MAX),
+            Object::Float(f64::NEG_INFINITY),
+            Object::Float(-9_223_372_036_854_775_808.0),
+            Object::Float(-0.5),
+            Object::Float(-0.0),
+            Object::Float(0.0),
+            Object::Float(1.0),
+            Object::Float((1u64 << 53) as f64),
+            Object::Float(9_223_372_036_854_775_808.0),
+            Object::Float(f64::INFINITY),
+            Object::Float(f64::NAN),
+            Object::Float(-f64::NAN),
+        ];
+        let cmp = |a: &Object, b: &Object| a.partial_cmp(b).unwrap();
+        for a in &values {
+            for b in &values {
+                assert_eq!(cmp(a, b), cmp(b, a).reverse());
+                assert_eq!(cmp(a, b).is_eq(), a == b, "{:?} {:?}", a, b);
+                for c in &values {
+                    if cmp(a, b).is_le() && cmp(b, c).is_le() {
+                        assert!(cmp(a, c).is_le(), "{:?} {:?} {:?}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_object_try_from() -> Result<(), EvalError> {
+        let env = Env::new();
+        let n: i64 = eval(ast!((* 6 7)), &env)?.try_into()?;
+        assert_eq!(n, 42);
+        let f: f64 = eval(ast!((/ 3.0 2)), &env)?.try_into()?;
+        assert_eq!(f, 1.5);
+        let b: bool = eval(ast!((< 1 2)), &env)?.try_into()?;
+        assert!(b);
+        let s: String = eval(ast!((+ "a" "b")), &env)?.try_into()?;
+        assert_eq!(s, "ab");
+
+        assert_eq!(
+            i64::try_from(Object::Bool(true)),
+            Err(EvalError::TypeMismatch(
+                "expected Num, but got Bool(true)".to_string()
+            ))
+        );
+        assert!(bool::try_from(Object::Num(1)).is_err());
+        assert!(String::try_from(Object::Nil).is_err());
+        assert!(f64::try_from(Object::Num(1)).is_err());
+        Ok(())
+    }
+
+    // Function の持つ Env は RefCell だが、ハッシュ値には使っていない
+    #[allow(clippy::mutable_key_type)]
+    #[test]
+    fn test_object_hash() {
+        use std::collections::HashMap;
+
+        let mut memo: HashMap<Object, Object> = HashMap::new();
+        for n in 0..10 {
+            memo.insert(Object::Num(n), Object::Num(n * n));
+        }
+        assert_eq!(memo.get(&Object::Num(7)), Some(&Object::Num(49)));
+        assert_eq!(memo.get(&Object::Num(10)), None);
+
+        memo.insert(Object::Str("a".to_string()), Object::Bool(true));
+        memo.insert(Object::Bool(false), Object::Nil);
+        memo.insert(
+            Object::List(vec![Object::Num(1), Object::Num(2)]),
+            Object::Num(3),
+        );
+        memo.insert(Object::Float(0.0), Object::Num(0));
+        assert_eq!(
+            memo.get(&Object::Str("a".to_string())),
+            Some(&Object::Bool(true))
+        );
+        assert_eq!(memo.get(&Object::Bool(false)), Some(&Object::Nil));
+        assert_eq!(
+            memo.get(&Object::List(vec![Object::Num(1), Object::Num(2)])),
+            Some(&Object::Num(3))
+        );
+        assert_eq!(memo.get(&Object::Float(-0.0)), Some(&Object::Num(0)));
+        // Num と Float は別のキー
+        assert_eq!(memo.get(&Object::Float(1.0)), None);
+
+        // NaN も自分自身と等しいので、キーにして取り出せる
+        let nan = Object::Float(f64::NAN);
+        assert_eq!(nan, nan.clone());
+        assert_eq!(nan, Object::Float(-f64::NAN));
+        // 大小の比較も == と食い違わないように、NaN どうしは Equal になる
+        // ほかの数とも比べられ、NaN はどの数より大きい
+        assert_eq!(
+            nan.partial_cmp(&Object::Float(-f64::NAN)),
+            Some(std::cmp::Ordering::Equal)
+        );
+        assert_eq!(
+            nan.partial_cmp(&Object::Float(1.0)),
+            Some(std::cmp::Ordering::Greater)
+        );
+        let env = Env::new();
+        env.define("nan".to_string(), nan.clone());
+        assert_eq!(eval(ast!((== nan nan)), &env), Ok(Object::Bool(true)));
+        assert_eq!(eval(ast!((<= nan nan)), &env), Ok(Object::Bool(true)));
+        assert_eq!(eval(ast!((< nan nan)), &env), Ok(Object::Bool(false)));
+        assert_eq!(eval(ast!((< nan 1.0)), &env), Ok(Object::Bool(false)));
+        memo.insert(nan, Object::Str("nan".to_string()));
+        assert_eq!(
+            memo.get(&Object::Float(f64::NAN)),
+            Some(&Object::Str("nan".to_string()))
+        );
+        assert_eq!(
+            memo.get(&Object::Float(-f64::NAN)),
+            Some(&Object::Str("nan".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_object_display() {
+        assert_eq!(format!("{}", Object::Num(5)), "5");
+        assert_eq!(format!("{}", Object::Num(-5)), "-5");
+        assert_eq!(format!("{}", Object::Float(1.5)), "1.5");
+        assert_eq!(format!("{}", Object::Float(4.0)), "4.0");
+        assert_eq!(format!("{}", Object::Bool(true)), "true");
+        assert_eq!(format!("{}", Object::Bool(false)), "false");
+        assert_eq!(format!("{}", Object::Str("a b".to_string())), "a b");
+        assert_eq!(format!("{}", Object::Nil), "nil");
+        assert_eq!(
+            format!(
+                "{}",
+                Object::List(vec![
+                    Object::Num(1),
+                    Object::List(vec![Object::Bool(true)]),
+                    Object::Str("a".to_string())
+                ])
+            ),
+            "(1 (true) a)"
+        );
+
+        let env = Env::new();
+        let f = eval(ast!((Func (a b) (+ a b))), &env).unwrap();
+        assert_eq!(format!("{}", f), "#<function/2>");
+        let g = eval(ast!((Func () 1)), &env).unwrap();
+        assert_eq!(format!("{}", g), "#<function/0>");
+    }
+
+    #[test]
+    fn test_eval_error_display() {
+        assert_eq!(
+            EvalError::Undefined("foo".to_string()).to_string(),
+            "undefined identifier: foo"
+        );
+        assert_eq!(
+            EvalError::TypeMismatch("expected Num".to_string()).to_string(),
+            "type mismatch: expected Num"
+        );
+        assert_eq!(
+            EvalError::NotApplicable(Object::Num(1)).to_string(),
+            "not applicable: 1"
+        );
+        assert_eq!(EvalError::DivByZero.to_string(), "division by zero");
+        assert_eq!(EvalError::EmptyList.to_string(), "empty list");
+        assert_eq!(
+            EvalError::AssertionFailed("x > 0".to_string()).to_string(),
+            "assertion failed: x > 0"
+        );
+        assert_eq!(
+            EvalError::UnknownArgument("c".to_string()).to_string(),
+            "unknown argument: c"
+        );
+        assert_eq!(
+            EvalError::DuplicateArgument("a".to_string()).to_string(),
+            "duplicate argument: a"
+        );
+        assert_eq!(
+            EvalError::IndexOutOfBounds { index: 3, len: 2 }.to_string(),
+            "index out of bounds: the len is 2 but the index is 3"
+        );
+        assert_eq!(EvalError::Overflow.to_string(), "arithmetic overflow");
+        assert_eq!(
+            EvalError::StepLimitExceeded.to_string(),
+            "step limit exceeded"
+        );
+        assert_eq!(
+            EvalError::Arity {
+                expected: 2,
+                got: 1
+            }
+            .to_string(),
+            "wrong number of arguments: expected 2, got 1"
+        );
+
+        let err: Box<dyn std::error::Error> = Box::new(EvalError::DivByZero);
+        assert_eq!(err.to_string(), "division by zero");
+    }
+
+    #[test]
+    fn test_eval_with_env() {
+        let env = Env::new();
+        let value = eval(ast!((Define x 1)), &env).unwrap();
+
+        assert_eq!(value, Object::Nil);
+        assert_eq!(env.get("x"), Some(Object::Num(1)));
+
+        assert_eq!(eval(ast!(x), &env).unwrap(), Object::Num(1));
+        assert_eq!(eval(ast!((+ 3 x)), &env).unwrap(), Object::Num(4));
+
+        let env = Env::new();
+        let plus_two = ast!((Define plus_two (Func (x) (+ x 2))));
+        eval(plus_two, &env).unwrap();
+
+        let app = ast!((Apply plus_two 3));
+        let obj = eval(app, &env).unwrap();
+        assert_eq!(obj, Object::Num(5));
+
+        let f = ast!((Define f (Func (a b) (+ a (+ b 1)))));
+        eval(f, &env).unwrap();
+        let f_app = ast!((Apply f 10 20));
+        assert_eq!(eval(f_app, &env).unwrap(), Object::Num(31));
+
+        let f_app = ast!((Apply (Func (a b) (+ a (+ b 1))) 100 200));
+        assert_eq!(eval(f_app, &env).unwrap(), Object::Num(301));
+
+        let g = ast!((Define g (Func (y) (If (== y 0) 1000 (Apply f 10 y)))));
+        eval(g, &env).unwrap();
+
+        let g_app = ast!((Apply g 500));
+        assert_eq!(eval(g_app, &env).unwrap(), Object::Num(511));
+
+        // 外側の x に引数の x が上書きされない
+        eval(ast!((Define x 99)), &env).unwrap();
+        let shadow = ast!((Apply (Func (x) x) 5));
+        assert_eq!(eval(shadow, &env).unwrap(), Object::Num(5));
+        assert_eq!(env.get("x"), Some(Object::Num(99)));
+
+        // 返された関数が外側の関数の引数を覚えている
+        let env = Env::new();
+        let make_adder = ast!((Define make_adder (Func (n) (Func (x) (+ x n)))));
+        eval(make_adder, &env).unwrap();
+        eval(ast!((Define add5 (Apply make_adder 5))), &env).unwrap();
+        assert_eq!(eval(ast!((Apply add5 10)), &env).unwrap(), Object::Num(15));
+        assert_eq!(env.get("n"), None);
+
+        // 本体は順番に評価されて最後の値が返る
+        let env = Env::new();
+        let f = ast!((Define f (Func (x) (Define y (* x 2)) (+ y 1))));
+        eval(f, &env).unwrap();
+        assert_eq!(eval(ast!((Apply f 5)), &env).unwrap(), Object::Num(11));
+        assert_eq!(env.get("y"), None);
+    }
+
+    #[test]
+    fn test_eval_if_scope() {
+        let env = Env::new();
+        assert_eq!(
+            eval(ast!((If true (Begin (Define tmp 1) tmp) 0)), &env).unwrap(),
+            Object::Num(1)
+        );
+        assert_eq!(env.get("tmp"), None);
+        assert_eq!(
+            eval(ast!(tmp), &env),
+            Err(EvalError::Undefined("tmp".to_string()))
+        );
+        assert_eq!(
+            eval(ast!((If false 0 (Begin (Define tmp 2) tmp))), &env).unwrap(),
+            Object::Num(2)
+        );
+        assert_eq!(env.get("tmp"), None);
+
+        // 外側の名前は見えるし、Set! は外側の束縛を書き換える
+        eval(ast!((Define x 1)), &env).unwrap();
+        eval(ast!((If (== x 1) (Set! x (+ x 10)) nil)), &env).unwrap();
+        assert_eq!(env.get("x"), Some(Object::Num(11)));
+
+        // 分岐の中の Define は外側の同じ名前を隠すだけ
+        assert_eq!(
+            eval(ast!((If true (Begin (Define x 5) x) x)), &env).unwrap(),
+            Object::Num(5)
+        );
+        assert_eq!(env.get("x"), Some(Object::Num(11)));
+
+        // Cond と Case の節も同じ
+        let branches = [
+            ast!((Cond (true (Begin (Define tmp 1) tmp)) 0)),
+            ast!((Cond (false 0) (Begin (Define tmp 1) tmp))),
+            ast!((Case 1 (1 (Begin (Define tmp 1) tmp)) 0)),
+            ast!((Case 2 (1 0) (Begin (Define tmp 1) tmp))),
+        ];
+        for branch in branches {
+            assert_eq!(eval(branch, &env).unwrap(), Object::Num(1));
+            assert_eq!(env.get("tmp"), None);
+        }
+        eval(ast!((Cond ((== x 11) (Set! x 12)) nil)), &env).unwrap();
+        assert_eq!(env.get("x"), Some(Object::Num(12)));
+    }
+
+    #[test]
+    fn test_eval_cond() {
+        let env = Env::new();
+        let cond = ast!((Cond ((== 1 2) 10) ((== 1 1) 20) 30));
+        assert_eq!(eval(cond, &env).unwrap(), Object::Num(20));
+
+        let cond = ast!((Cond ((== 1 2) 10) (0 20) 30));
+        assert_eq!(eval(cond, &env).unwrap(), Object::Num(30));
+
+        // 最初にマッチした節より後ろは評価されない
+        let cond = ast!((Cond (true 1) ((/ 1 0) 2) 3));
+        assert_eq!(eval(cond, &env).unwrap(), Object::Num(1));
+    }
+
+    #[test]
+    fn test_eval_case() {
+        let env = Env::new();
+        eval(
+            ast!((Define name (Func (n) (Case n (1 "one") (2 "two") ((+ 1 2) "three") "many")))),
+            &env,
+        )
+        .unwrap();
+        for (n, expected) in [(1, "one"), (2, "two"), (3, "three"), (4, "many")] {
+            assert_eq!(
+                eval(
+                    AST::Apply {
+                        fn_lit: Box::new(ast!(name)),
+                        args: vec![AST::Num(n)],
+                        named: vec![]
+                    },
+                    &env
+                )
+                .unwrap(),
+                Object::Str(expected.to_string())
+            );
+        }
+        assert_eq!(
+            eval(ast!((Case 1 "default")), &env).unwrap(),
+            Object::Str("default".to_string())
+        );
+
+        // scrutinee は一度しか評価されない
+        eval(ast!((Define count 0)), &env).unwrap();
+        let program = ast!(
+        (Case (Begin (Set! count (+ count 1)) count)
+            (0 "zero")
+            (2 "two")
+            "one"));
+        assert_eq!(eval(program, &env).unwrap(), Object::Str("one".to_string()));
+        assert_eq!(env.get("count"), Some(Object::Num(1)));
+
+        // 一致した節より後ろの key は評価されない
+        assert_eq!(
+            eval(ast!((Case 1 (1 10) ((/ 1 0) 20) 30)), &env).unwrap(),
+            Object::Num(10)
+        );
+    }
+
+    #[test]
+    fn test_eval_begin() {
+        let env = Env::new();
+        let begin = ast!((Begin (Define x 5) (+ x 1)));
+        assert_eq!(eval(begin, &env).unwrap(), Object::Num(6));
+        assert_eq!(env.get("x"), Some(Object::Num(5)));
+
+        assert_eq!(eval(AST::Begin(vec![]), &env).unwrap(), Object::Nil);
+    }
+
+    #[test]
+    fn test_eval_set() {
+        let env = Env::new();
+        assert_eq!(
+            eval(ast!((Set! x 1)), &env),
+            Err(EvalError::Undefined("x".to_string()))
+        );
+        assert_eq!(env.get("x"), None);
+
+        eval(ast!((Define x 0)), &env).unwrap();
+        assert_eq!(eval(ast!((Set! x 1)), &env).unwrap(), Object::Num(1));
+        assert_eq!(env.get("x"), Some(Object::Num(1)));
+
+        // 関数の中から外側の変数を書き換えられる
+        let inc = ast!((Define inc (Func () (Set! x (+ x 1)))));
+        eval(inc, &env).unwrap();
+        eval(ast!((Apply inc)), &env).unwrap();
+        eval(ast!((Apply inc)), &env).unwrap();
+        assert_eq!(env.get("x"), Some(Object::Num(3)));
+    }
+
+    #[test]
+    fn test_eval_list() {
+        let env = Env::new();
+        let list = ast!((cons 1 (cons 2 nil)));
+        assert_eq!(
+            eval(list, &env).unwrap(),
+            Object::List(vec![Object::Num(1), Object::Num(2)])
+        );
+
+        eval(ast!((Define l (cons 1 (cons 2 nil)))), &env).unwrap();
+        assert_eq!(eval(ast!((car l)), &env).unwrap(), Object::Num(1));
+        assert_eq!(
+            eval(ast!((cdr l)), &env).unwrap(),
+            Object::List(vec![Object::Num(2)])
+        );
+        assert_eq!(eval(ast!((car (cdr l))), &env).unwrap(), Object::Num(2));
+        // 最後の要素の cdr は nil になる
+        assert_eq!(eval(ast!((cdr (cdr l))), &env).unwrap(), Object::Nil);
+
+        assert_eq!(eval(ast!((car nil)), &env), Err(EvalError::EmptyList));
+        assert_eq!(eval(ast!((cdr nil)), &env), Err(EvalError::EmptyList));
+        assert!(matches!(
+            eval(ast!((car 1)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_pair() {
+        let env = Env::new();
+        let pair = eval(ast!((cons 1 2)), &env).unwrap();
+        assert_eq!(
+            pair,
+            Object::Pair(Box::new(Object::Num(1)), Box::new(Object::Num(2)))
+        );
+        assert_eq!(pair.to_string(), "(1 . 2)");
+        assert_eq!(eval(ast!((car (cons 1 2))), &env).unwrap(), Object::Num(1));
+        assert_eq!(eval(ast!((cdr (cons 1 2))), &env).unwrap(), Object::Num(2));
+        assert_eq!(
+            eval(ast!((TypeOf (cons 1 2))), &env).unwrap(),
+            Object::Str("pair".to_string())
+        );
+
+        // 最後が nil なら今までどおり List になる
+        let proper = eval(ast!((cons 1 (cons 2 nil))), &env).unwrap();
+        assert_eq!(proper, Object::List(vec![Object::Num(1), Object::Num(2)]));
+        assert_eq!(proper.to_string(), "(1 2)");
+
+        let improper = eval(ast!((cons 1 (cons 2 3))), &env).unwrap();
+        assert_eq!(improper.to_string(), "(1 2 . 3)");
+        assert_eq!(
+            eval(ast!((car (cdr (cons 1 (cons 2 3))))), &env).unwrap(),
+            Object::Num(2)
+        );
+        assert_eq!(
+            eval(ast!((cdr (cdr (cons 1 (cons 2 3))))), &env).unwrap(),
+            Object::Num(3)
+        );
+        assert_eq!(
+            eval(ast!((cons (cons 1 2) (list 3))), &env)
+                .unwrap()
+                .to_string(),
+            "((1 . 2) 3)"
+        );
+        assert_eq!(
+            eval(ast!((== (cons 1 2) (cons 1 2))), &env).unwrap(),
+            Object::Bool(true)
+        );
+
+        // リストとして扱う操作には渡せない
+        assert!(matches!(
+            eval(ast!((length (cons 1 2))), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval(ast!((map (Func (x) x) (cons 1 2))), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_length() {
+        let env = Env::new();
+        assert_eq!(
+            eval(ast!((length (list 1 (list 2 3) 4))), &env).unwrap(),
+            Object::Num(3)
+        );
+        assert_eq!(eval(ast!((length(list))), &env).unwrap(), Object::Num(0));
+        assert_eq!(eval(ast!((length "abc")), &env).unwrap(), Object::Num(3));
+        // バイト数ではなく文字の数を数える
+        assert_eq!(eval(ast!((length "あいう")), &env).unwrap(), Object::Num(3));
+        assert_eq!(eval(ast!((length "")), &env).unwrap(), Object::Num(0));
+        assert!(matches!(
+            eval(ast!((length 1)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_nth() {
+        let env = Env::new();
+        eval(ast!((Define l (list 10 20 30))), &env).unwrap();
+        assert_eq!(eval(ast!((nth l 0)), &env).unwrap(), Object::Num(10));
+        assert_eq!(eval(ast!((nth l (+ 1 1))), &env).unwrap(), Object::Num(30));
+        assert_eq!(
+            eval(ast!((nth l 3)), &env),
+            Err(EvalError::IndexOutOfBounds { index: 3, len: 3 })
+        );
+        assert_eq!(
+            eval(ast!((nth l {-1})), &env),
+            Err(EvalError::IndexOutOfBounds { index: -1, len: 3 })
+        );
+        assert_eq!(
+            eval(ast!((nth (list) 0)), &env),
+            Err(EvalError::IndexOutOfBounds { index: 0, len: 0 })
+        );
+        assert!(matches!(
+            eval(ast!((nth l 1.0)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval(ast!((nth "abc" 0)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+
+        assert_eq!(eval(ast!((first l)), &env).unwrap(), Object::Num(10));
+        assert_eq!(
+            eval(ast!((rest l)), &env).unwrap(),
+            Object::List(vec![Object::Num(20), Object::Num(30)])
+        );
+        assert_eq!(eval(ast!((first nil)), &env), Err(EvalError::EmptyList));
+    }
+
+    #[test]
+    fn test_eval_list_form() {
+        let env = Env::new();
+        assert_eq!(
+            eval(ast!((list (+ 1 1) 3)), &env).unwrap(),
+            Object::List(vec![Object::Num(2), Object::Num(3)])
+        );
+        assert_eq!(
+            eval(ast!((list 1 (list "a" true) (car (list 'c')))), &env).unwrap(),
+            Object::List(vec![
+                Object::Num(1),
+                Object::List(vec![Object::Str("a".to_string()), Object::Bool(true)]),
+                Object::Char('c'),
+            ])
+        );
+        assert_eq!(eval(ast!((list)), &env).unwrap(), Object::Nil);
+        assert_eq!(
+            eval(ast!((== (list 1 (+ 1 1)) (quote (1 2)))), &env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((list 1 x)), &env),
+            Err(EvalError::Undefined("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_eval_map() {
+        let env = Env::from(crate::prelude());
+        assert_eq!(
+            eval(ast!((map (Func (x) (+ x 1)) (list 1 2 3))), &env).unwrap(),
+            Object::List(vec![Object::Num(2), Object::Num(3), Object::Num(4)])
+        );
+        assert_eq!(
+            eval(ast!((map abs (quote ({-1} 2)))), &env).unwrap(),
+            Object::List(vec![Object::Num(1), Object::Num(2)])
+        );
+        // 空のリストには関数を一度も適用しない
+        assert_eq!(
+            eval(ast!((map (Func (x) (/ x 0)) (list))), &env).unwrap(),
+            Object::Nil
+        );
+        assert!(matches!(
+            eval(ast!((map (Func (x) x) 1)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(
+            eval(ast!((map (Func (x) (/ x 0)) (list 1))), &env),
+            Err(EvalError::DivByZero)
+        );
+        assert_eq!(
+            eval(ast!((map (Func (a b) a) (list 1))), &env),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_eval_fold() {
+        let env = Env::from(crate::prelude());
+        assert_eq!(
+            eval(ast!((fold (Func (acc x) (+ acc x)) 0 (list 1 2 3))), &env).unwrap(),
+            Object::Num(6)
+        );
+        // 左から順に畳み込む
+        assert_eq!(
+            eval(ast!((fold (Func (acc x) (- acc x)) 10 (list 1 2 3))), &env).unwrap(),
+            Object::Num(4)
+        );
+        assert_eq!(
+            eval(
+                ast!((fold (Func (acc x) (cons x acc)) nil (list 1 2 3))),
+                &env
+            )
+            .unwrap(),
+            Object::List(vec![Object::Num(3), Object::Num(2), Object::Num(1)])
+        );
+        assert_eq!(
+            eval(ast!((fold max 0 (quote (3 7 5)))), &env).unwrap(),
+            Object::Num(7)
+        );
+        // 空のリストなら init をそのまま返す
+        assert_eq!(
+            eval(ast!((fold (Func (acc x) (/ acc 0)) "init" (list))), &env).unwrap(),
+            Object::Str("init".to_string())
+        );
+        assert!(matches!(
+            eval(ast!((fold max 0 "abc")), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(
+            eval(ast!((fold (Func (x) x) 0 (list 1))), &env),
+            Err(EvalError::Arity {
+                expected: 1,
+                got: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_eval_quote() {
+        let env = Env::new();
+        assert_eq!(
+            eval(ast!((quote (1 2 3))), &env).unwrap(),
+            Object::List(vec![Object::Num(1), Object::Num(2), Object::Num(3)])
+        );
+        assert_eq!(
+            eval(ast!((quote (1 (true "a") ()))), &env).unwrap(),
+            Object::List(vec![
+                Object::Num(1),
+                Object::List(vec![Object::Bool(true), Object::Str("a".to_string())]),
+                Object::Nil,
+            ])
+        );
+        assert_eq!(eval(ast!((quote 1.5)), &env).unwrap(), Object::Float(1.5));
+        assert_eq!(eval(ast!((quote())), &env).unwrap(), Object::Nil);
+        assert_eq!(
+            eval(ast!((car (cdr (quote (1 2))))), &env).unwrap(),
+            Object::Num(2)
+        );
+        // 識別子はまだデータにできない
+        assert!(matches!(
+            eval(ast!((quote (1 x))), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_variadic() {
+        let env = Env::new();
+        eval(ast!((Define f (Func (a . rest) (cons a rest)))), &env).unwrap();
+        assert_eq!(
+            eval(ast!((Apply f 1 2 3)), &env).unwrap(),
+            Object::List(vec![Object::Num(1), Object::Num(2), Object::Num(3)])
+        );
+        eval(ast!((Define g (Func (a . rest) rest))), &env).unwrap();
+        assert_eq!(
+            eval(ast!((Apply g 1 2 3)), &env).unwrap(),
+            Object::List(vec![Object::Num(2), Object::Num(3)])
+        );
+        // 残りの引数がなければ nil になる
+        assert_eq!(eval(ast!((Apply g 1)), &env).unwrap(), Object::Nil);
+        assert_eq!(
+            eval(ast!((Apply g)), &env),
+            Err(EvalError::Arity {
+                expected: 1,
+                got: 0
+            })
+        );
+
+        let sum = ast!(
+        (Define sum
+            (Func (. nums)
+                (If (== nums nil)
+                    0
+                    (+ (car nums) (Apply sum_list (cdr nums)))))));
+        eval(sum, &env).unwrap();
+        let sum_list = ast!(
+        (Define sum_list
+            (Func (nums)
+                (If (== nums nil)
+                    0
+                    (+ (car nums) (Apply sum_list (cdr nums)))))));
+        eval(sum_list, &env).unwrap();
+        assert_eq!(
+            eval(ast!((Apply sum 1 2 3 4)), &env).unwrap(),
+            Object::Num(10)
+        );
+        assert_eq!(eval(ast!((Apply sum)), &env).unwrap(), Object::Num(0));
+    }
+
+    #[test]
+    fn test_eval_native() {
+        let env = Env::new();
+        register_builtin(&env, "square", |args| match args.as_slice() {
+            [Object::Num(v)] => Ok(Object::Num(v * v)),
+            _ => Err(EvalError::TypeMismatch("square takes a Num".to_string())),
+        });
+        assert_eq!(eval(ast!((Apply square 5)), &env).unwrap(), Object::Num(25));
+        assert_eq!(
+            eval(
+                ast!((Apply (Func (f x) (Apply f (Apply f x))) square 3)),
+                &env
+            )
+            .unwrap(),
+            Object::Num(81)
+        );
+        assert!(matches!(
+            eval(ast!((Apply square true)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(
+            format!("{}", env.get("square").unwrap()),
+            "#<native square>"
+        );
+    }
+
+    #[test]
+    fn test_apply_function() {
+        let env = Env::from(prelude());
+        eval(ast!((Defun hypot2 (a b) (+ (* a a) (* b b)))), &env).unwrap();
+        let func = env.get("hypot2").unwrap();
+        assert_eq!(
+            apply_function(func.clone(), vec![Object::Num(3), Object::Num(4)]),
+            eval(ast!((Apply hypot2 3 4)), &env)
+        );
+        assert_eq!(
+            apply_function(func.clone(), vec![Object::Num(3)]),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 1
+            })
+        );
+
+        // 関数は定義されたときの環境を見る
+        eval(ast!((Define k 10)), &env).unwrap();
+        let add_k = eval(ast!((Func (x) (+ x k))), &env).unwrap();
+        eval(ast!((Set! k 20)), &env).unwrap();
+        assert_eq!(
+            apply_function(add_k, vec![Object::Num(1)]),
+            Ok(Object::Num(21))
+        );
+
+        assert_eq!(
+            apply_function(env.get("abs").unwrap(), vec![Object::Num(-2)]),
+            Ok(Object::Num(2))
+        );
+        assert_eq!(
+            apply_function(Object::Num(1), vec![]),
+            Err(EvalError::NotApplicable(Object::Num(1)))
+        );
+    }
+
+    #[test]
+    fn test_eval_named_args() {
+        let env = Env::from(crate::prelude());
+        eval(ast!((Define sub (Func (a b) (- a b)))), &env).unwrap();
+        assert_eq!(
+            eval(ast!((Apply sub b: 2 a: 5)), &env),
+            eval(ast!((Apply sub 5 2)), &env)
+        );
+        assert_eq!(
+            eval(ast!((Apply sub 5 b: 2)), &env).unwrap(),
+            Object::Num(3)
+        );
+        assert_eq!(
+            eval(
+                ast!((Apply (Func (a b . rest) (list a b rest)) 1 2 3 4)),
+                &env
+            )
+            .unwrap(),
+            eval(ast!((quote (1 2 (3 4)))), &env).unwrap()
+        );
+        assert_eq!(
+            eval(
+                ast!((Apply (Func (a b . rest) (list a b rest)) b: 2 a: 1)),
+                &env
+            )
+            .unwrap(),
+            eval(ast!((quote (1 2 nil))), &env).unwrap()
+        );
+
+        assert_eq!(
+            eval(ast!((Apply sub a: 1 c: 2)), &env),
+            Err(EvalError::UnknownArgument("c".to_string()))
+        );
+        assert_eq!(
+            eval(ast!((Apply sub 1 a: 2)), &env),
+            Err(EvalError::DuplicateArgument("a".to_string()))
+        );
+        assert_eq!(
+            eval(ast!((Apply sub a: 1 a: 2)), &env),
+            Err(EvalError::DuplicateArgument("a".to_string()))
+        );
+        assert_eq!(
+            eval(ast!((Apply sub b: 1)), &env),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert_eq!(
+            eval(ast!((Apply abs v: 1)), &env),
+            Err(EvalError::UnknownArgument("v".to_string()))
+        );
+        assert_eq!(
+            eval(ast!((Apply 1 a: 1)), &env),
+            Err(EvalError::NotApplicable(Object::Num(1)))
+        );
+    }
+
+    #[test]
+    fn test_eval_apply_list() {
+        let env = Env::from(crate::prelude());
+        eval(ast!((Define add (Func (a b) (+ a b)))), &env).unwrap();
+        assert_eq!(
+            eval(ast!((apply-list add (list 1 2))), &env),
+            eval(ast!((Apply add 1 2)), &env)
+        );
+        assert_eq!(
+            eval(ast!((apply-list max (cons 3 (list 5)))), &env).unwrap(),
+            Object::Num(5)
+        );
+        assert_eq!(
+            eval(
+                ast!((apply-list (Func (. rest) rest) (quote (1 2 3)))),
+                &env
+            )
+            .unwrap(),
+            Object::List(vec![Object::Num(1), Object::Num(2), Object::Num(3)])
+        );
+        // nil は引数なしで呼ぶ
+        assert_eq!(
+            eval(ast!((apply-list (Func () 1) nil)), &env).unwrap(),
+            Object::Num(1)
+        );
+        assert!(matches!(
+            eval(ast!((apply-list add 1)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(
+            eval(ast!((apply-list add (list 1))), &env),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_eval_let() {
+        let env = Env::new();
+        let let_ast = ast!((Let x (+ 1 2) (+ x x)));
+        assert_eq!(eval(let_ast, &env).unwrap(), Object::Num(6));
+        assert_eq!(env.get("x"), None);
+
+        // 外側の x は Let の中でだけ隠される
+        eval(ast!((Define x 10)), &env).unwrap();
+        let shadow = ast!((Let x 1 (+ x (Let y x y))));
+        assert_eq!(eval(shadow, &env).unwrap(), Object::Num(2));
+        assert_eq!(env.get("x"), Some(Object::Num(10)));
+        assert_eq!(env.get("y"), None);
+    }
+
+    #[test]
+    fn test_eval_let_list() {
+        let env = Env::new();
+        assert_eq!(
+            eval(ast!((Let (a b) (list 1 2) (+ a b))), &env).unwrap(),
+            Object::Num(3)
+        );
+        assert_eq!(env.get("a"), None);
+        assert_eq!(
+            eval(ast!((Let (x y) (list "a" (list 1)) (cons y x))), &env).unwrap(),
+            Object::Pair(
+                Box::new(Object::List(vec![Object::Num(1)])),
+                Box::new(Object::Str("a".to_string()))
+            )
+        );
+        assert_eq!(eval(ast!((Let () nil 1)), &env).unwrap(), Object::Num(1));
+
+        assert_eq!(
+            eval(ast!((Let (a b) (list 1 2 3) a)), &env),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 3
+            })
+        );
+        assert_eq!(
+            eval(ast!((Let (a b) (list 1) a)), &env),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert!(matches!(
+            eval(ast!((Let (a) 1 a)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_min_max() {
+        let env = Env::new();
+        assert_eq!(eval(ast!((min 3 5)), &env).unwrap(), Object::Num(3));
+        assert_eq!(eval(ast!((max 3 5)), &env).unwrap(), Object::Num(5));
+        assert_eq!(eval(ast!((min 5 3)), &env).unwrap(), Object::Num(3));
+        assert_eq!(eval(ast!((max 5 3)), &env).unwrap(), Object::Num(5));
+        assert_eq!(
+            eval(ast!((max (min 1.5 2.5) 0.5)), &env).unwrap(),
+            Object::Float(1.5)
+        );
+        assert!(matches!(
+            eval(ast!((min 1 true)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(eval(ast!((max 1 1.5)), &env).unwrap(), Object::Float(1.5));
+        assert_eq!(eval(ast!((min 2 1.5)), &env).unwrap(), Object::Float(1.5));
+    }
+
+    #[test]
+    fn test_eval_type_of() {
+        let env = Env::from(prelude());
+        let cases = vec![
+            (ast!((TypeOf 1)), "num"),
+            (ast!((TypeOf 1.5)), "float"),
+            (ast!((TypeOf true)), "bool"),
+            (ast!((TypeOf "a")), "str"),
+            (ast!((TypeOf 'a')), "char"),
+            (ast!((TypeOf nil)), "nil"),
+            (ast!((TypeOf (quote (1 2)))), "list"),
+            (ast!((TypeOf (Func (x) x))), "function"),
+            (ast!((TypeOf abs)), "function"),
+            (ast!((TypeOf (TypeOf 1))), "str"),
+        ];
+        for (program, expected) in cases {
+            assert_eq!(
+                eval(program, &env).unwrap(),
+                Object::Str(expected.to_string())
+            );
+        }
+        assert_eq!(
+            eval(ast!((If (== (TypeOf x) "num") 1 2)), &env),
+            Err(EvalError::Undefined("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_eval_char() {
+        let env = Env::new();
+        assert_eq!(ast!('a'), AST::Char('a'));
+        assert_eq!(eval(ast!('a'), &env).unwrap(), Object::Char('a'));
+        assert_eq!(eval(ast!((== 'a' 'a')), &env).unwrap(), Object::Bool(true));
+        assert_eq!(eval(ast!((== 'a' 'b')), &env).unwrap(), Object::Bool(false));
+        assert_eq!(eval(ast!((== 'a' "a")), &env).unwrap(), Object::Bool(false));
+        assert_eq!(eval(ast!((< 'a' 'b')), &env).unwrap(), Object::Bool(true));
+        assert_eq!(
+            eval(ast!((quote ('x' 1))), &env).unwrap(),
+            Object::List(vec![Object::Char('x'), Object::Num(1)])
+        );
+        assert!(matches!(
+            eval(ast!((+ 'a' 'b')), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(format!("{}", Object::Char('a')), "a");
+        assert_eq!(format!("{}", ast!((== 'a' ' '))), r"(== #\a #\ )");
+    }
+
+    #[test]
+    fn test_eval_equal() {
+        let env = Env::new();
+        env.define("f".to_string(), eval(ast!((Func (x) x)), &env).unwrap());
+        env.define("g".to_string(), eval(ast!((Func (x) x)), &env).unwrap());
+        env.define("h".to_string(), eval(ast!((Func (y) y)), &env).unwrap());
+        let local = eval(ast!((Let z 1 (Func (x) x))), &env).unwrap();
+        env.define("local".to_string(), local);
+
+        let cases = vec![
+            (ast!((== 1 1)), true),
+            (ast!((== 1 2)), false),
+            (ast!((== 1.5 1.5)), true),
+            // 数値どうしでも Num と Float は等しくならない
+            (ast!((== 1 1.0)), false),
+            (ast!((== true true)), true),
+            (ast!((== true false)), false),
+            // 真偽値としての扱いと違って、数値と真偽値は等しくならない
+            (ast!((== 1 true)), false),
+            (ast!((== 0 false)), false),
+            (ast!((== nil false)), false),
+            (ast!((== nil nil)), true),
+            (ast!((== "a" "a")), true),
+            (ast!((== "1" 1)), false),
+            (ast!((== (quote (1 2)) (cons 1 (cons 2 nil)))), true),
+            (ast!((== (quote (1 2)) (quote (1 2.0)))), false),
+            (ast!((== f f)), true),
+            // 同じ環境で作った同じ形の関数は等しい
+            (ast!((== f g)), true),
+            (ast!((== f h)), false),
+            // 環境が違えば同じ形でも等しくない
+            (ast!((== f local)), false),
+            (ast!((== f 1)), false),
+        ];
+        for (program, expected) in cases {
+            assert_eq!(
+                eval(program.clone(), &env).unwrap(),
+                Object::Bool(expected),
+                "{}",
+                program
+            );
+            // != は == の逆になる
+            let AST::Equal(left, right) = program else {
+                unreachable!()
+            };
+            assert_eq!(
+                eval(AST::NotEqual(left, right), &env).unwrap(),
+                Object::Bool(!expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval_not_equal() {
+        let env = Env::new();
+        assert_eq!(eval(ast!((!= 1 2)), &env).unwrap(), Object::Bool(true));
+        assert_eq!(eval(ast!((!= 1 1)), &env).unwrap(), Object::Bool(false));
+        assert_eq!(
+            eval(ast!((If (!= "a" "b") 1 2)), &env).unwrap(),
+            Object::Num(1)
+        );
+        assert_eq!(
+            eval(ast!((!= x 1)), &env),
+            Err(EvalError::Undefined("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_eval_while() {
+        let env = Env::new();
+        let program = vec![
+            ast!((Define counter 10)),
+            ast!((Define total 0)),
+            ast!((While (> counter 0)
+                (Begin
+                    (Set! total (+ total counter))
+                    (Set! counter (- counter 1))))),
+        ];
+        assert_eq!(eval_program(program, &env).unwrap(), Object::Nil);
+        assert_eq!(env.get("counter"), Some(Object::Num(0)));
+        assert_eq!(env.get("total"), Some(Object::Num(55)));
+
+        // 条件がはじめから偽なら body は評価されない
+        assert_eq!(
+            eval(ast!((While false (Set! undefined 1))), &env).unwrap(),
+            Object::Nil
+        );
+        assert!(matches!(
+            eval(ast!((While "a" 1)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(
+            eval_with_limit(ast!((While true 1)), &env, 1000),
+            Err(EvalError::StepLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn test_eval_do_times() {
+        let env = Env::new();
+        eval(ast!((Define seen nil)), &env).unwrap();
+        let program = ast!((DoTimes (i 3) (Set! seen (cons i seen))));
+        // 最後に評価した body の値を返す
+        assert_eq!(
+            eval(program, &env).unwrap(),
+            Object::List(vec![Object::Num(2), Object::Num(1), Object::Num(0)])
+        );
+        assert_eq!(env.get("i"), None);
+
+        // 回数が 0 以下なら body を評価せず nil を返す
+        assert_eq!(
+            eval(ast!((DoTimes (i 0) (/ 1 0))), &env).unwrap(),
+            Object::Nil
+        );
+        assert_eq!(
+            eval(ast!((DoTimes (i {-1}) (/ 1 0))), &env).unwrap(),
+            Object::Nil
+        );
+
+        // 繰り返しごとに別の i を覚えている
+        eval(ast!((Define fs nil)), &env).unwrap();
+        eval(
+            ast!((DoTimes (i (+ 1 1)) (Set! fs (cons (Func () i) fs)))),
+            &env,
+        )
+        .unwrap();
+        assert_eq!(
+            eval(ast!((list (Apply (car fs)) (Apply (car (cdr fs))))), &env).unwrap(),
+            Object::List(vec![Object::Num(1), Object::Num(0)])
+        );
+
+        assert!(matches!(
+            eval(ast!((DoTimes (i 1.5) i)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_eval_assert() {
+        let env = Env::new();
+        eval(ast!((Define x 3)), &env).unwrap();
+        assert_eq!(
+            eval(ast!((Assert (> x 0) "x must be positive")), &env).unwrap(),
+            Object::Nil
+        );
+        assert_eq!(
+            eval(ast!((Assert (< x 0) "x must be negative")), &env),
+            Err(EvalError::AssertionFailed("x must be negative".to_string()))
+        );
+        // 条件が真なら msg は評価しない
+        assert_eq!(
+            eval(ast!((Assert true (/ 1 0))), &env).unwrap(),
+            Object::Nil
+        );
+        assert_eq!(
+            eval(ast!((Assert (== x 4) (list "x is" x))), &env),
+            Err(EvalError::AssertionFailed("(x is 3)".to_string()))
+        );
+        // 途中で失敗したら残りは評価されない
+        assert_eq!(
+            eval(ast!((Begin (Assert false "stop") (Set! x 10))), &env),
+            Err(EvalError::AssertionFailed("stop".to_string()))
+        );
+        assert_eq!(env.get("x"), Some(Object::Num(3)));
+        assert!(matches!(
+            eval(ast!((Assert "a" "msg")), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_function_body_shared() {
+        let env = Env::new();
+        eval(
+            ast!((Define f (Func (n)
+                (Define a (+ n 1))
+                (Define b (* a 2))
+                (Define c (- b 3))
+                (If (> c 10) (+ a (+ b c)) (list a b c))))),
+            &env,
+        )
+        .unwrap();
+        let body = match env.get("f") {
+            Some(Object::Function { body, .. }) => body,
+            obj => panic!("expected a function, but got {:?}", obj),
+        };
+        // 環境の中の関数と取り出した関数が同じ本体を指している
+        assert_eq!(Rc::strong_count(&body), 2);
+
+        eval(
+            ast!((Begin (Define i 0) (While (< i 100) (Begin (Apply f i) (Set! i (+ i 1)))))),
+            &env,
+        )
+        .unwrap();
+        // 呼び出すたびに本体が複製されていれば、環境の中の本体は別のものになる
+        match env.get("f") {
+            Some(Object::Function { body: after, .. }) => assert!(Rc::ptr_eq(&body, &after)),
+            obj => panic!("expected a function, but got {:?}", obj),
+        }
+        assert_eq!(Rc::strong_count(&body), 2);
+    }
+
+    #[test]
+    fn test_eval_mutual_recursion() {
+        let env = Env::new();
+        // is_even を定義した時点では is_odd はまだないが、呼び出すときに環境から探すので見つかる
+        let program = vec![
+            ast!((Define is_even (Func (n) (If (== n 0) true (Apply is_odd (- n 1)))))),
+            ast!((Define is_odd (Func (n) (If (== n 0) false (Apply is_even (- n 1)))))),
+        ];
+        eval_program(program, &env).unwrap();
+        assert_eq!(
+            eval(ast!((Apply is_even 10)), &env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((Apply is_odd 7)), &env).unwrap(),
+            Object::Bool(true)
+        );
+        // 末尾呼び出しなので深くてもスタックは伸びない
+        assert_eq!(
+            eval(ast!((Apply is_even 100001)), &env).unwrap(),
+            Object::Bool(false)
+        );
+    }
+
+    #[test]
+    fn test_eval_letrec() {
+        let env = Env::new();
+        let program = ast!(
+        (LetRec ((is_even (Func (n) (If (== n 0) true (Apply is_odd (- n 1)))))
+                 (is_odd (Func (n) (If (== n 0) false (Apply is_even (- n 1))))))
+            (and (Apply is_even 10) (not (Apply is_odd 10)))));
+        assert_eq!(eval(program, &env).unwrap(), Object::Bool(true));
+        // 外側の環境には残らない
+        assert_eq!(env.get("is_even"), None);
+
+        // 後ろの束縛は前の値を使える
+        assert_eq!(
+            eval(ast!((LetRec ((a 1) (b (+ a 1))) (* a b))), &env).unwrap(),
+            Object::Num(2)
+        );
+        // 値を評価する前は nil が入っている
+        assert_eq!(
+            eval(ast!((LetRec ((a b) (b 1)) a)), &env).unwrap(),
+            Object::Nil
+        );
+    }
+
+    #[test]
+    fn test_eval_ref() {
+        let env = Env::new();
+        eval(ast!((Define x 0)), &env).unwrap();
+        let inc = ast!((Set! x (+ x 1)));
+        assert_eq!(eval_ref(&inc, &env).unwrap(), Object::Num(1));
+        assert_eq!(eval_ref(&inc, &env).unwrap(), Object::Num(2));
+
+        let ast = ast!((Apply (Func (a b) (* a b)) 6 7));
+        assert_eq!(eval_ref(&ast, &env).unwrap(), Object::Num(42));
+        assert_eq!(eval_ref(&ast, &env).unwrap(), Object::Num(42));
+        assert_eq!(ast, ast!((Apply (Func (a b) (* a b)) 6 7)));
+    }
+
+    #[test]
+    fn test_env_snapshot() {
+        let env = Env::new();
+        eval(ast!((Define y 1)), &env).unwrap();
+        let snapshot = env.snapshot();
+        eval(ast!((Define x 1)), &env).unwrap();
+        eval(ast!((Set! y 2)), &env).unwrap();
+        assert_eq!(env.get("x"), Some(Object::Num(1)));
+        env.restore(snapshot);
+        assert_eq!(env.get("x"), None);
+        assert_eq!(env.get("y"), Some(Object::Num(1)));
+
+        // 親のスコープへの Set! も元に戻る
+        let inner = env.child();
+        inner.define("z".to_string(), Object::Num(0));
+        let snapshot = inner.snapshot();
+        eval(ast!((Begin (Set! y 10) (Set! z 20) (Define w 30))), &inner).unwrap();
+        assert_eq!(env.get("y"), Some(Object::Num(10)));
+        inner.restore(snapshot);
+        assert_eq!(env.get("y"), Some(Object::Num(1)));
+        assert_eq!(inner.get("z"), Some(Object::Num(0)));
+        assert_eq!(inner.get("w"), None);
+    }
+
+    #[test]
+    fn test_eval_scope() {
+        use std::collections::HashMap;
+
+        // 読み出した名前を記録する Scope
+        // get は &self なので記録は RefCell に入れる
+        #[derive(Default)]
+        struct LoggingScope {
+            vars: HashMap<String, Object>,
+            log: RefCell<Vec<String>>,
+        }
+
+        impl Scope for LoggingScope {
+            fn get(&self, k: &str) -> Option<Object> {
+                self.log.borrow_mut().push(k.to_string());
+                self.vars.get(k).cloned()
+            }
+
+            fn set(&mut self, k: String, v: Object) {
+                self.vars.insert(k, v);
+            }
+        }
+
+        let scope = Rc::new(RefCell::new(LoggingScope::default()));
+        let var = |name: &str| scope.borrow().vars.get(name).cloned();
+        let log = || scope.borrow().log.take();
+        scope
+            .borrow_mut()
+            .vars
+            .insert("x".to_string(), Object::Num(1));
+        scope
+            .borrow_mut()
+            .vars
+            .insert("y".to_string(), Object::Num(2));
+        assert_eq!(eval_scope(ast!((+ x (* y x))), &scope), Ok(Object::Num(3)));
+        // 読むたびに scope から読む
+        assert_eq!(log(), vec!["x", "y", "x"]);
+
+        // Set! は束縛されているかを scope で確かめてから書き込み、Define はそのまま書き込む
+        assert_eq!(
+            eval_scope(ast!((Begin (Set! x (+ x 10)) (Define z (* y 3)) x)), &scope),
+            Ok(Object::Num(11))
+        );
+        assert_eq!(log(), vec!["x", "x", "y", "x"]);
+        assert_eq!(var("x"), Some(Object::Num(11)));
+        assert_eq!(var("z"), Some(Object::Num(6)));
+
+        assert_eq!(
+            eval_scope(ast!(w), &scope),
+            Err(EvalError::Undefined("w".to_string()))
+        );
+        assert_eq!(log(), vec!["w"]);
+
+        // 関数は scope を見続けるので、あとで scope を書き換えると関数からも見える
+        scope
+            .borrow_mut()
+            .vars
+            .insert("k".to_string(), Object::Num(1));
+        eval_scope(
+            ast!((Begin (Define get_k (Func () k)) (Define set_k (Func (v) (Set! k v))))),
+            &scope,
+        )
+        .unwrap();
+        scope
+            .borrow_mut()
+            .vars
+            .insert("k".to_string(), Object::Num(100));
+        assert_eq!(
+            eval_scope(ast!((Apply get_k)), &scope),
+            Ok(Object::Num(100))
+        );
+        // 関数の中の Set! も scope の k を書き換える
+        eval_scope(ast!((Apply set_k 5)), &scope).unwrap();
+        assert_eq!(var("k"), Some(Object::Num(5)));
+        // 引数は関数を呼んだときのスコープに束縛されるので、scope には書き込まれない
+        assert_eq!(var("v"), None);
+
+        // HashMap と Env もそのまま Scope として使える
+        let vars = Rc::new(RefCell::new(HashMap::new()));
+        vars.borrow_mut().insert("n".to_string(), Object::Num(5));
+        eval_scope(ast!((Define m (+ n 1))), &vars).unwrap();
+        assert_eq!(vars.borrow().get("m"), Some(&Object::Num(6)));
+
+        let env = Env::new();
+        env.define("n".to_string(), Object::Num(5));
+        let shared = Rc::new(RefCell::new(env.clone()));
+        eval_scope(ast!((Begin (Define m (- n 1)) (Set! n 0))), &shared).unwrap();
+        assert_eq!(env.get("m"), Some(Object::Num(4)));
+        assert_eq!(env.get("n"), Some(Object::Num(0)));
+    }
+
     #[test]
-    fn test_eval() {
-        let mut empty_env = HashMap::new();
-        let ast = AST::Num(1);
-        assert_eq!(eval(ast, &mut empty_env), Object::Num(1));
+    fn test_eval_many() {
+        let envs: Vec<Env> = [1, 2, 3]
+            .into_iter()
+            .map(|x| {
+                let env = Env::new();
+                env.define("x".to_string(), Object::Num(x));
+                env
+            })
+            .collect();
+        let ast = ast!((* x 10));
+        assert_eq!(
+            eval_many(&ast, &envs),
+            vec![
+                Ok(Object::Num(10)),
+                Ok(Object::Num(20)),
+                Ok(Object::Num(30))
+            ]
+        );
 
-        // (1 + 2)
-        // (+ 1 2)
-        let simple_add = AST::Add(Box::new(AST::Num(1)), Box::new(AST::Num(2)));
-        assert_eq!(eval(simple_add, &mut empty_env), Object::Num(3));
+        // 環境ごとに別々に Define される
+        let results = eval_many(&ast!((Define y (+ x 1))), &envs);
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(envs[2].get("y"), Some(Object::Num(4)));
 
-        // ((((1 + 2) + 3) + 4) + 5)
-        // (+ (+ (+ (+ 1 2) 3) 4 ) 5)
-        let complicated_add = AST::Add(
-            Box::new(AST::Add(
-                Box::new(AST::Add(
-                    Box::new(AST::Add(Box::new(AST::Num(1)), Box::new(AST::Num(2)))),
-                    Box::new(AST::Num(3)),
-                )),
-                Box::new(AST::Num(4)),
-            )),
-            Box::new(AST::Num(5)),
+        envs[1].set("x", Object::Str("a".to_string()));
+        let results = eval_many(&ast, &envs);
+        assert_eq!(results[0], Ok(Object::Num(10)));
+        assert!(matches!(results[1], Err(EvalError::TypeMismatch(_))));
+        assert_eq!(results[2], Ok(Object::Num(30)));
+        assert!(eval_many(&ast, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_eval_print() {
+        let env = Env::new();
+        assert_eq!(eval(ast!((Print (+ 1 2))), &env).unwrap(), Object::Num(3));
+        assert_eq!(
+            eval(ast!((+ (Print 1) (Print 2))), &env).unwrap(),
+            Object::Num(3)
         );
+    }
 
-        assert_eq!(eval(complicated_add, &mut empty_env), Object::Num(15));
+    #[test]
+    fn test_eval_with_limit() {
+        let env = Env::new();
+        eval(
+            ast!((Define forever (Func (n) (Apply forever (+ n 1))))),
+            &env,
+        )
+        .unwrap();
+        assert_eq!(
+            eval_with_limit(ast!((Apply forever 0)), &env, 10_000),
+            Err(EvalError::StepLimitExceeded)
+        );
 
+        // 末尾呼び出しでない再帰もスタックを使い切る前に止まる
+        eval(ast!((Define deep (Func (n) (+ 1 (Apply deep n))))), &env).unwrap();
         assert_eq!(
-            eval(
-                // ((1 + 2) - 2)
-                // (- (+ 1 2) 2)
-                ast!((- (+ 1 2) 2)),
-                &mut empty_env
-            ),
-            Object::Num(1)
+            eval_with_limit(ast!((Apply deep 0)), &env, 100),
+            Err(EvalError::StepLimitExceeded)
+        );
+
+        assert_eq!(
+            eval_with_limit(ast!((+ 1 2)), &env, 3).unwrap(),
+            Object::Num(3)
+        );
+        assert_eq!(
+            eval_with_limit(ast!((+ 1 2)), &env, 2),
+            Err(EvalError::StepLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn test_eval_counting() {
+        let env = Env::new();
+        assert_eq!(
+            eval_counting(ast!((+ 1 2)), &env).unwrap(),
+            (Object::Num(3), 3)
+        );
+        assert_eq!(eval_counting(ast!(1), &env).unwrap(), (Object::Num(1), 1));
+        // 選ばれなかった分岐は数えない
+        assert_eq!(
+            eval_counting(ast!((If true 1 (+ 2 3))), &env).unwrap(),
+            (Object::Num(1), 3)
         );
 
-        assert_eq!(eval(ast!(true), &mut empty_env), Object::Bool(true));
-        assert_eq!(eval(ast!(false), &mut empty_env), Object::Bool(false));
+        // 関数の本体も呼ぶたびに数える
+        eval(ast!((Define inc (Func (n) (+ n 1)))), &env).unwrap();
+        let (obj, once) = eval_counting(ast!((Apply inc 1)), &env).unwrap();
+        assert_eq!(obj, Object::Num(2));
+        let (_, twice) = eval_counting(ast!((Apply inc (Apply inc 1))), &env).unwrap();
+        assert!(twice > once);
+
+        // 同じ数だけのステップがあれば eval_with_limit でも評価し終わる
+        let program = ast!((Apply inc (Apply inc 1)));
+        assert!(eval_with_limit(program.clone(), &env, twice).is_ok());
+        assert_eq!(
+            eval_with_limit(program, &env, twice - 1),
+            Err(EvalError::StepLimitExceeded)
+        );
 
-        assert_eq!(eval(ast!((If true 1 2)), &mut empty_env), Object::Num(1));
-        assert_eq!(eval(ast!((If false 1 2)), &mut empty_env), Object::Num(2));
+        assert_eq!(
+            eval_counting(ast!((/ 1 0)), &env),
+            Err(EvalError::DivByZero)
+        );
+    }
 
-        assert_eq!(eval(ast!((If 1 1 2)), &mut empty_env), Object::Num(1));
-        assert_eq!(eval(ast!((If 0 1 2)), &mut empty_env), Object::Num(2));
+    #[test]
+    fn test_eval_traced() {
+        let env = Env::new();
+        let mut trace = vec![];
+        let value = eval_traced(ast!((+ 1 2)), &env, &mut trace).unwrap();
+        assert_eq!(value, Object::Num(3));
+        assert_eq!(
+            trace,
+            vec![
+                (ast!(1), Object::Num(1)),
+                (ast!(2), Object::Num(2)),
+                (ast!((+ 1 2)), Object::Num(3)),
+            ]
+        );
 
+        eval(ast!((Define double (Func (n) (* n 2)))), &env).unwrap();
+        let mut trace = vec![];
+        eval_traced(ast!((Apply double (+ 1 2))), &env, &mut trace).unwrap();
+        let trace: Vec<String> = trace
+            .iter()
+            .map(|(ast, obj)| format!("{} => {}", ast, obj))
+            .collect();
         assert_eq!(
-            eval(ast!((== 3 (+ 1 2))), &mut empty_env),
-            Object::Bool(true)
+            trace,
+            vec![
+                "double => #<function/1>",
+                "1 => 1",
+                "2 => 2",
+                "(+ 1 2) => 3",
+                "n => 3",
+                "2 => 2",
+                "(* n 2) => 6",
+                "(Apply double (+ 1 2)) => 6",
+            ]
         );
+
+        // 選ばれた分岐も、関数の本体の最後の式も、末尾位置の Apply も記録される
+        eval(
+            ast!((Define abs (Func (x) (If (< x 0) (- 0 x) (Apply double x))))),
+            &env,
+        )
+        .unwrap();
+        let mut trace = vec![];
+        eval_traced(ast!((Apply abs 4)), &env, &mut trace).unwrap();
+        let trace: Vec<String> = trace
+            .iter()
+            .map(|(ast, obj)| format!("{} => {}", ast, obj))
+            .collect();
+        assert_eq!(
+            trace,
+            vec![
+                "abs => #<function/1>",
+                "4 => 4",
+                "x => 4",
+                "0 => 0",
+                "(< x 0) => false",
+                "double => #<function/1>",
+                "x => 4",
+                "n => 4",
+                "2 => 2",
+                "(* n 2) => 8",
+                "(Apply double x) => 8",
+                "(If (< x 0) (- 0 x) (Apply double x)) => 8",
+                "(Apply abs 4) => 8",
+            ]
+        );
+
+        let mut trace = vec![];
+        assert_eq!(
+            eval_traced(ast!((+ (* 2 3) (/ 1 0))), &env, &mut trace),
+            Err(EvalError::DivByZero)
+        );
+        assert_eq!(
+            trace,
+            vec![
+                (ast!(2), Object::Num(2)),
+                (ast!(3), Object::Num(3)),
+                (ast!((* 2 3)), Object::Num(6)),
+                (ast!(1), Object::Num(1)),
+                (ast!(0), Object::Num(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_eval_tail_call() {
+        // 末尾呼び出しはループになるのでスタックを使い切らない
+        let env = Env::new();
+        let countdown = ast!(
+        (Define countdown
+            (Func (n acc)
+                (If (== n 0)
+                    acc
+                    (Apply countdown (- n 1) (+ acc 1))))));
+        eval(countdown, &env).unwrap();
+        assert_eq!(
+            eval(ast!((Apply countdown 100000 0)), &env).unwrap(),
+            Object::Num(100000)
+        );
+
+        // Cond や Begin や Let の最後も末尾位置になる
+        let even = ast!(
+        (Define even
+            (Func (n)
+                (Cond ((== n 0) true)
+                    (Let m (- n 1)
+                        (Begin m (Apply odd m)))))));
+        let odd = ast!((Define odd (Func (n) (If (== n 0) false (Apply even (- n 1))))));
+        eval(even, &env).unwrap();
+        eval(odd, &env).unwrap();
         assert_eq!(
-            eval(ast!((== 0 (+ 1 2))), &mut empty_env),
+            eval(ast!((Apply even 100001)), &env).unwrap(),
             Object::Bool(false)
         );
     }
 
     #[test]
-    fn test_eval_with_env() {
-        let mut env = HashMap::new();
-        let value = eval(ast!((Define x 1)), &mut env);
+    fn test_eval_program() {
+        let env = Env::new();
+        let forms = vec![
+            ast!((Define sum (Func (n) (If (== n 1) 1 (+ n (Apply sum (- n 1))))))),
+            ast!((Apply sum 5)),
+        ];
+        assert_eq!(eval_program(forms, &env).unwrap(), Object::Num(15));
+        assert!(env.get("sum").is_some());
 
-        assert_eq!(value, Object::Num(1));
-        assert_eq!(env.get("x"), Some(&Object::Num(1)));
+        let forms = parser::parse_program("(Define x 2) (Define y 3) (* x y)").unwrap();
+        assert_eq!(eval_program(forms, &env).unwrap(), Object::Num(6));
+        assert_eq!(eval_program(vec![], &env).unwrap(), Object::Nil);
+    }
 
-        assert_eq!(eval(ast!(x), &mut env), Object::Num(1));
-        assert_eq!(eval(ast!((+ 3 x)), &mut env), Object::Num(4));
+    #[test]
+    fn test_ast_macro_prelude() {
+        // prelude の関数を呼ぶ省略形は、どれも @prelude を通して同じ Apply になる
+        let apply = |name: &str, arg: AST| AST::Apply {
+            fn_lit: Box::new(AST::Ident(name.to_string())),
+            args: vec![arg],
+            named: vec![],
+        };
+        assert_eq!(ast!((bool->num (< 1 2))), apply("bool->num", ast!((< 1 2))));
+        assert_eq!(ast!((num->bool x)), apply("num->bool", ast!(x)));
+        assert_eq!(ast!((bool->num x)), ast!(@prelude "bool->num" x));
+        assert_eq!(ast!((num->bool 0)), ast!(@prelude "num->bool" 0));
 
-        let mut env = HashMap::new();
-        let plus_two = ast!((Define plus_two (Func (x) (+ x 2))));
-        eval(plus_two, &mut env);
+        let env = Env::from(prelude());
+        assert_eq!(
+            eval(ast!((num->bool (bool->num true))), &env).unwrap(),
+            Object::Bool(true)
+        );
+    }
 
-        let app = ast!((Apply plus_two 3));
-        let obj = eval(app, &mut env);
-        assert_eq!(obj, Object::Num(5));
+    #[test]
+    fn test_eval_deep_recursion() {
+        // 呼び出しのたびに環境をコピーしないので、呼び出しの回数に比例した時間で終わる
+        // デバッグビルドでは末尾呼び出しでない再帰一段で数十 KB のスタックを使うので、
+        // 深さはふつうのスレッドのスタック 8MB に収まる程度にしておく
+        std::thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(|| {
+                let env = Env::new();
+                // 呼び出しごとのコピーが遅くなるように、外側の環境に束縛をたくさん置いておく
+                for i in 0..1000 {
+                    env.define(format!("v{}", i), Object::Num(i));
+                }
+                let sum = ast!(
+                (Define sum
+                    (Func (n)
+                        (If (== n 1)
+                            1
+                            (+ n (Apply sum (- n 1)))))));
+                eval(sum, &env).unwrap();
+                assert_eq!(
+                    eval(ast!((Apply sum 100)), &env).unwrap(),
+                    Object::Num(5050)
+                );
 
-        let f = ast!((Define f (Func (a b) (+ a (+ b 1)))));
-        eval(f, &mut env);
-        let f_app = ast!((Apply f 10 20));
-        assert_eq!(eval(f_app, &mut env), Object::Num(31));
+                // 末尾呼び出しならスタックを使わないので、呼び出しの回数を増やせる
+                let sum_to = ast!(
+                (Define sum_to
+                    (Func (n acc)
+                        (If (== n 0)
+                            acc
+                            (Apply sum_to (- n 1) (+ acc n))))));
+                eval(sum_to, &env).unwrap();
+                assert_eq!(
+                    eval(ast!((Apply sum_to 100000 0)), &env).unwrap(),
+                    Object::Num(5000050000)
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
 
-        let f_app = ast!((Apply (Func (a b) (+ a (+ b 1))) 100 200));
-        assert_eq!(eval(f_app, &mut env), Object::Num(301));
+    #[test]
+    fn test_ast_display() {
+        assert_eq!(format!("{}", ast!((+ (+ 1 2) 3))), "(+ (+ 1 2) 3)");
+        // lexer が読めるエスケープだけを使い、ほかの文字はそのまま書く
+        assert_eq!(
+            AST::Str("a\"b\\c\nd\te".to_string()).to_string(),
+            r#""a\"b\\c\nd\te""#
+        );
+        for s in ["\r\n", "\u{1b}[0m", "\0", "it's", "\\r"] {
+            let ast = AST::Str(s.to_string());
+            assert_eq!(parser::parse(&ast.to_string()).unwrap(), ast);
+        }
+        assert_eq!(format!("{}", ast!((- 1.5 x))), "(- 1.5 x)");
+        assert_eq!(format!("{}", AST::Num(-5)), "-5");
+        assert_eq!(format!("{}", ast!((If x nil 1))), "(If x nil 1)");
+        assert_eq!(
+            format!("{}", ast!((cons (car x) (cdr nil)))),
+            "(cons (car x) (cdr nil))"
+        );
+        assert_eq!(format!("{}", ast!((== "a" "\"b\""))), r#"(== "a" "\"b\"")"#);
+        assert_eq!(
+            format!("{}", ast!((If (<= x 1) (and true x) (not false)))),
+            "(If (<= x 1) (and true x) (not false))"
+        );
+        assert_eq!(
+            format!("{}", ast!((Cond ((== x 1) 10) 20))),
+            "(Cond ((== x 1) 10) 20)"
+        );
+        assert_eq!(
+            format!("{}", ast!((Define f (Func (a b) (Set! a 1) (* a b))))),
+            "(Define f (Func (a b) (Set! a 1) (* a b)))"
+        );
+        assert_eq!(format!("{}", ast!((Func () 1))), "(Func () 1)");
+        assert_eq!(format!("{}", ast!((Func (a . b) b))), "(Func (a . b) b)");
+        assert_eq!(format!("{}", ast!((Func (. b) b))), "(Func (. b) b)");
+        assert_eq!(format!("{}", ast!((Print (+ 1 2)))), "(Print (+ 1 2))");
+        assert_eq!(format!("{}", ast!((TypeOf x))), "(TypeOf x)");
+        assert_eq!(
+            format!("{}", ast!((Assert (> x 0) "positive"))),
+            r#"(Assert (> x 0) "positive")"#
+        );
+        assert_eq!(format!("{}", ast!((length "a"))), r#"(length "a")"#);
+        assert_eq!(format!("{}", ast!((nth x 1))), "(nth x 1)");
+        assert_eq!(format!("{}", ast!((!= x 1))), "(!= x 1)");
+        assert_eq!(
+            format!("{}", ast!((Apply f 1 b: (+ 1 2) c: x))),
+            "(Apply f 1 b: (+ 1 2) c: x)"
+        );
+        assert_eq!(
+            format!("{}", ast!((apply-list f (list 1 2)))),
+            "(apply-list f (list 1 2))"
+        );
+        assert_eq!(format!("{}", ast!((list 1 (+ 1 x)))), "(list 1 (+ 1 x))");
+        assert_eq!(format!("{}", ast!((min 1 (max 2 3)))), "(min 1 (max 2 3))");
+        assert_eq!(format!("{}", ast!((map f (list 1)))), "(map f (list 1))");
+        assert_eq!(format!("{}", ast!((fold f 0 xs))), "(fold f 0 xs)");
+        assert_eq!(
+            format!("{}", ast!((quote (1 ("a" ()) nil)))),
+            r#"(quote (1 ("a" ()) nil))"#
+        );
+        assert_eq!(
+            format!("{}", ast!((Let x 1 (Begin (Apply f x) (Apply g))))),
+            "(Let x 1 (Begin (Apply f x) (Apply g)))"
+        );
+        assert_eq!(
+            format!("{}", ast!((Case x (1 "a") (2 "b") nil))),
+            r#"(Case x (1 "a") (2 "b") nil)"#
+        );
+        assert_eq!(
+            format!("{}", ast!((While (< i 3) (Set! i (+ i 1))))),
+            "(While (< i 3) (Set! i (+ i 1)))"
+        );
+        assert_eq!(
+            format!("{}", ast!((DoTimes (i 3) (Print i)))),
+            "(DoTimes (i 3) (Print i))"
+        );
+        assert_eq!(
+            format!("{}", ast!((LetRec ((a 1) (b a)) b))),
+            "(LetRec ((a 1) (b a)) b)"
+        );
 
-        let g = ast!((Define g (Func (y) (If (== y 0) 1000 (Apply f 10 y)))));
-        eval(g, &mut env);
+        let program = "(Define f (Func (n) (If (< n 1) 0 (Apply f (% n 2)))))";
+        assert_eq!(format!("{}", parser::parse(program).unwrap()), program);
+    }
 
-        let g_app = ast!((Apply g 500));
-        assert_eq!(eval(g_app, &mut env), Object::Num(511));
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_ast_serde() {
+        let ast = ast!((If (== 1 2) 3 4));
+        let json = serde_json::to_string(&ast).unwrap();
+        assert_eq!(serde_json::from_str::<AST>(&json).unwrap(), ast);
+
+        let ast = ast!((Define f (Func (a b) (Cond ((< a b) a) b))));
+        let json = serde_json::to_string(&ast).unwrap();
+        assert_eq!(serde_json::from_str::<AST>(&json).unwrap(), ast);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_object_to_json() {
+        let env = Env::new();
+        let obj = eval(ast!((list 1 (list 2.5 "a" true) nil 'c' (list))), &env).unwrap();
+        assert_eq!(
+            serde_json::Value::try_from(obj).unwrap(),
+            serde_json::json!([1, [2.5, "a", true], null, "c", null])
+        );
+
+        let func = eval(ast!((list 1 (Func (x) x))), &env).unwrap();
+        assert!(matches!(
+            serde_json::Value::try_from(func),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(serde_json::Value::try_from(Object::Float(f64::NAN)).is_err());
+        let pair = eval(ast!((cons 1 2)), &env).unwrap();
+        assert!(serde_json::Value::try_from(pair).is_err());
     }
 
     #[test]
@@ -264,8 +3454,51 @@ mod tests {
             AST::Minus(Box::new(AST::Num(10)), Box::new(AST::Num(5)))
         );
 
+        assert_eq!(
+            ast!((* 6 7)),
+            AST::Mul(Box::new(AST::Num(6)), Box::new(AST::Num(7)))
+        );
+        assert_eq!(
+            ast!((/ 10 3)),
+            AST::Div(Box::new(AST::Num(10)), Box::new(AST::Num(3)))
+        );
+        assert_eq!(
+            ast!((% 10 3)),
+            AST::Mod(Box::new(AST::Num(10)), Box::new(AST::Num(3)))
+        );
+        assert_eq!(
+            ast!((* (/ 8 2) (- 3 x))),
+            AST::Mul(
+                Box::new(AST::Div(Box::new(AST::Num(8)), Box::new(AST::Num(2)))),
+                Box::new(AST::Minus(
+                    Box::new(AST::Num(3)),
+                    Box::new(AST::Ident("x".to_string()))
+                )),
+            )
+        );
+
+        assert_eq!(ast!(3.5), AST::Float(3.5));
+        assert_eq!(ast!(-5), AST::Num(-5));
+        assert_eq!(ast!(-2.5), AST::Float(-2.5));
+        assert_eq!(
+            ast!((- {-5} 1)),
+            AST::Minus(Box::new(AST::Num(-5)), Box::new(AST::Num(1)))
+        );
+        assert_eq!(ast!("a"), AST::Str("a".to_string()));
+        assert_eq!(AST::from("a".to_string()), AST::Str("a".to_string()));
+
         assert_eq!(ast!(true), AST::Bool(true));
         assert_eq!(ast!(false), AST::Bool(false));
+        assert_eq!(ast!(nil), AST::Nil);
+        assert_eq!(
+            ast!((cons 1 (cdr (car x)))),
+            AST::Cons(
+                Box::new(AST::Num(1)),
+                Box::new(AST::Cdr(Box::new(AST::Car(Box::new(AST::Ident(
+                    "x".to_string()
+                ))))))
+            )
+        );
         assert_eq!(
             ast!((If 1 2 3)),
             AST::If {
@@ -279,6 +3512,98 @@ mod tests {
             ast!((== 1 2)),
             AST::Equal(Box::new(AST::Num(1)), Box::new(AST::Num(2)))
         );
+        assert_eq!(
+            ast!((and true false)),
+            AST::And(Box::new(AST::Bool(true)), Box::new(AST::Bool(false)))
+        );
+        assert_eq!(
+            ast!((or true false)),
+            AST::Or(Box::new(AST::Bool(true)), Box::new(AST::Bool(false)))
+        );
+        assert_eq!(ast!((not true)), AST::Not(Box::new(AST::Bool(true))));
+        assert_eq!(
+            ast!((< 1 2)),
+            AST::Less(Box::new(AST::Num(1)), Box::new(AST::Num(2)))
+        );
+        assert_eq!(
+            ast!((> 1 2)),
+            AST::Greater(Box::new(AST::Num(1)), Box::new(AST::Num(2)))
+        );
+        assert_eq!(
+            ast!((<= 1 2)),
+            AST::LessEq(Box::new(AST::Num(1)), Box::new(AST::Num(2)))
+        );
+        assert_eq!(
+            ast!((>= 1 2)),
+            AST::GreaterEq(Box::new(AST::Num(1)), Box::new(AST::Num(2)))
+        );
+        assert_eq!(
+            ast!((Case x (1 10) ((+ 1 1) 20) 0)),
+            AST::Case {
+                scrutinee: Box::new(ast!(x)),
+                arms: vec![(ast!(1), ast!(10)), (ast!((+ 1 1)), ast!(20))],
+                default: Box::new(ast!(0)),
+            }
+        );
+        assert_eq!(
+            ast!((While (> x 0) (Set! x (- x 1)))),
+            AST::While {
+                cond: Box::new(ast!((> x 0))),
+                body: Box::new(ast!((Set! x (- x 1)))),
+            }
+        );
+        assert_eq!(
+            ast!((LetRec ((f (Func () (Apply g))) (g (Func () 1))) (Apply f))),
+            AST::LetRec {
+                bindings: vec![
+                    ("f".to_string(), ast!((Func () (Apply g)))),
+                    ("g".to_string(), ast!((Func () 1))),
+                ],
+                body: Box::new(ast!((Apply f))),
+            }
+        );
+        assert_eq!(
+            ast!((Let (x y) z x)),
+            AST::LetList {
+                names: vec!["x".to_string(), "y".to_string()],
+                value: Box::new(AST::Ident("z".to_string())),
+                body: Box::new(AST::Ident("x".to_string())),
+            }
+        );
+        assert_eq!(
+            ast!((Let x 1 (+ x 1))),
+            AST::Let {
+                name: "x".to_string(),
+                value: Box::new(AST::Num(1)),
+                body: Box::new(AST::Add(
+                    Box::new(AST::Ident("x".to_string())),
+                    Box::new(AST::Num(1))
+                )),
+            }
+        );
+        assert_eq!(
+            ast!((min 1 (max 2 x))),
+            AST::Min(
+                Box::new(AST::Num(1)),
+                Box::new(AST::Max(
+                    Box::new(AST::Num(2)),
+                    Box::new(AST::Ident("x".to_string()))
+                )),
+            )
+        );
+        assert_eq!(
+            ast!((>= (+ x 1) (< y 2))),
+            AST::GreaterEq(
+                Box::new(AST::Add(
+                    Box::new(AST::Ident("x".to_string())),
+                    Box::new(AST::Num(1))
+                )),
+                Box::new(AST::Less(
+                    Box::new(AST::Ident("y".to_string())),
+                    Box::new(AST::Num(2))
+                )),
+            )
+        );
 
         assert_eq!(
             ast!((Define x 1)),
@@ -288,6 +3613,119 @@ mod tests {
             }
         );
 
+        assert_eq!(
+            ast!((Cond ((== 1 2) 10) (true 20) 30)),
+            AST::Cond {
+                clauses: vec![
+                    (
+                        AST::Equal(Box::new(AST::Num(1)), Box::new(AST::Num(2))),
+                        AST::Num(10)
+                    ),
+                    (AST::Bool(true), AST::Num(20)),
+                ],
+                default: Box::new(AST::Num(30)),
+            }
+        );
+        assert_eq!(
+            ast!((Cond 30)),
+            AST::Cond {
+                clauses: vec![],
+                default: Box::new(AST::Num(30)),
+            }
+        );
+
+        assert_eq!(
+            ast!((Begin (Define x 1) x)),
+            AST::Begin(vec![
+                AST::Define {
+                    name: "x".to_string(),
+                    value: Box::new(AST::Num(1))
+                },
+                AST::Ident("x".to_string()),
+            ])
+        );
+
+        assert_eq!(
+            ast!((Set! x 1)),
+            AST::Set {
+                name: "x".to_string(),
+                value: Box::new(AST::Num(1))
+            }
+        );
+
+        assert_eq!(ast!((Print 1)), AST::Print(Box::new(AST::Num(1))));
+        assert_eq!(
+            ast!((bool->num x)),
+            parser::parse("(Apply bool->num x)").unwrap()
+        );
+        assert_eq!(
+            ast!((num->bool 1)),
+            parser::parse("(Apply num->bool 1)").unwrap()
+        );
+        assert_eq!(
+            ast!((list? (cons 1 nil))),
+            parser::parse("(Apply list? (cons 1 nil))").unwrap()
+        );
+        assert_eq!(
+            ast!((Defun square (x) (* x x))),
+            ast!((Define square (Func (x) (* x x))))
+        );
+        assert_eq!(
+            ast!((Defun f (a . rest) (Print a) rest)),
+            ast!((Define f (Func (a . rest) (Print a) rest)))
+        );
+        assert_eq!(
+            ast!((Defun zero () 0)),
+            AST::Define {
+                name: "zero".to_string(),
+                value: Box::new(AST::Function {
+                    params: vec![],
+                    rest: None,
+                    body: vec![AST::Num(0)]
+                })
+            }
+        );
+        assert_eq!(ast!((TypeOf 1)), AST::TypeOf(Box::new(AST::Num(1))));
+        assert_eq!(ast!((length x)), AST::Length(Box::new(ast!(x))));
+        assert_eq!(
+            ast!((nth x 1)),
+            AST::Nth(Box::new(ast!(x)), Box::new(ast!(1)))
+        );
+        assert_eq!(ast!((first x)), ast!((car x)));
+        assert_eq!(
+            ast!((Apply f x b: 2)),
+            AST::Apply {
+                fn_lit: Box::new(ast!(f)),
+                args: vec![ast!(x)],
+                named: vec![("b".to_string(), ast!(2))]
+            }
+        );
+        assert_eq!(
+            ast!((apply-list f xs)),
+            AST::ApplyList {
+                fn_lit: Box::new(ast!(f)),
+                args: Box::new(ast!(xs))
+            }
+        );
+        assert_eq!(ast!((rest x)), ast!((cdr x)));
+        assert_eq!(ast!((list 1 x)), AST::List(vec![ast!(1), ast!(x)]));
+        assert_eq!(ast!((list)), AST::List(vec![]));
+        assert_eq!(
+            ast!((map f xs)),
+            AST::Map {
+                func: Box::new(ast!(f)),
+                list: Box::new(ast!(xs))
+            }
+        );
+        assert_eq!(
+            ast!((fold f 0 xs)),
+            AST::Fold {
+                func: Box::new(ast!(f)),
+                init: Box::new(ast!(0)),
+                list: Box::new(ast!(xs))
+            }
+        );
+
         assert_eq!(ast!(x), AST::Ident("x".to_string()));
         assert_eq!(
             ast!((+ 1 x)),
@@ -298,7 +3736,17 @@ mod tests {
             ast!((Func () 2)),
             AST::Function {
                 params: vec![],
-                body: Box::new(AST::Num(2)),
+                rest: None,
+                body: vec![AST::Num(2)],
+            }
+        );
+
+        assert_eq!(
+            ast!((Func (x . xs) xs)),
+            AST::Function {
+                params: vec!["x".to_string()],
+                rest: Some("xs".to_string()),
+                body: vec![AST::Ident("xs".to_string())],
             }
         );
 
@@ -306,10 +3754,26 @@ mod tests {
             ast!((Func (x) (+ x 2))),
             AST::Function {
                 params: vec!["x".to_string()],
-                body: Box::new(AST::Add(
+                rest: None,
+                body: vec![AST::Add(
                     Box::new(AST::Ident("x".to_string())),
                     Box::new(AST::Num(2)),
-                ))
+                )]
+            }
+        );
+
+        assert_eq!(
+            ast!((Func (x) (Define y x) y)),
+            AST::Function {
+                params: vec!["x".to_string()],
+                rest: None,
+                body: vec![
+                    AST::Define {
+                        name: "y".to_string(),
+                        value: Box::new(AST::Ident("x".to_string())),
+                    },
+                    AST::Ident("y".to_string()),
+                ]
             }
         );
 
@@ -319,10 +3783,11 @@ mod tests {
                 name: "x".to_string(),
                 value: Box::new(AST::Function {
                     params: vec!["x".to_string(), "y".to_string()],
-                    body: Box::new(AST::Add(
+                    rest: None,
+                    body: vec![AST::Add(
                         Box::new(AST::Ident("y".to_string())),
                         Box::new(AST::Num(2)),
-                    ))
+                    )]
                 })
             }
         );
@@ -0,0 +1,147 @@
+use crate::visit::contains_define;
+use crate::{eval_ref, map_ast, truthy, Env, Object, AST};
+
+fn is_literal(ast: &AST) -> bool {
+    matches!(
+        ast,
+        AST::Num(_) | AST::Float(_) | AST::Bool(_) | AST::Str(_) | AST::Char(_) | AST::Nil
+    )
+}
+
+fn to_literal(obj: Object) -> Option<AST> {
+    match obj {
+        Object::Num(v) => Some(AST::Num(v)),
+        Object::Float(v) => Some(AST::Float(v)),
+        Object::Bool(b) => Some(AST::Bool(b)),
+        Object::Str(s) => Some(AST::Str(s)),
+        Object::Char(c) => Some(AST::Char(c)),
+        Object::Nil => Some(AST::Nil),
+        _ => None,
+    }
+}
+
+fn fold(ast: AST) -> AST {
+    let foldable = match &ast {
+        AST::Add(left, right)
+        | AST::Minus(left, right)
+        | AST::Mul(left, right)
+        | AST::Div(left, right)
+        | AST::Mod(left, right)
+        | AST::Equal(left, right)
+        | AST::NotEqual(left, right)
+        | AST::And(left, right)
+        | AST::Or(left, right)
+        | AST::Less(left, right)
+        | AST::Greater(left, right)
+        | AST::LessEq(left, right)
+        | AST::GreaterEq(left, right)
+        | AST::Min(left, right)
+        | AST::Max(left, right) => is_literal(left) && is_literal(right),
+        AST::Not(value) | AST::Negate(value) => is_literal(value),
+        // 分岐は子のスコープで評価されるので、Define を含む分岐を外に出すと名前が外に残ってしまう
+        // 関数の本体の中の Define なども含めて、Define があれば畳み込まない
+        AST::If { cond, then, els } => {
+            is_literal(cond) && !contains_define(then) && !contains_define(els)
+        }
+        _ => false,
+    };
+    if !foldable {
+        return ast;
+    }
+    match ast {
+        AST::If { cond, then, els } => match eval_ref(&cond, &Env::new()).and_then(truthy) {
+            Ok(true) => *then,
+            Ok(false) => *els,
+            // 評価したときに同じエラーになるように残しておく
+            Err(_) => AST::If { cond, then, els },
+        },
+        // 1 / 0 のようにエラーになるものも評価するまで残しておく
+        ast => match eval_ref(&ast, &Env::new()).ok().and_then(to_literal) {
+            Some(literal) => literal,
+            None => ast,
+        },
+    }
+}
+
+// 識別子を含まない部分を先に計算しておく
+// Ident や Apply、Define を含む部分は実行するまで値がわからないのでそのまま残る
+pub fn fold_constants(ast: AST) -> AST {
+    map_ast(ast, &mut fold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast, eval};
+
+    #[test]
+    fn test_fold_constants() {
+        assert_eq!(fold_constants(ast!((+ 1 2))), AST::Num(3));
+        assert_eq!(
+            fold_constants(ast!((* (+ 1 2) (- 10 (/ 9 3))))),
+            AST::Num(21)
+        );
+        assert_eq!(
+            fold_constants(ast!((and (< 1 2) (not false)))),
+            AST::Bool(true)
+        );
+        assert_eq!(fold_constants(ast!((+ "a" "b"))), ast!("ab"));
+        assert_eq!(fold_constants(ast!((!= 1 (+ 0 1)))), AST::Bool(false));
+        assert_eq!(fold_constants(ast!((neg (+ 2 3)))), AST::Num(-5));
+        assert_eq!(fold_constants(ast!((If true a b))), ast!(a));
+        assert_eq!(fold_constants(ast!((If (> 1 2) a (+ 1 1)))), AST::Num(2));
+    }
+
+    #[test]
+    fn test_fold_constants_preserve() {
+        let with_ident = ast!((+ x (* 2 3)));
+        assert_eq!(fold_constants(with_ident), ast!((+ x 6)));
+        let program = ast!((Define f (Func (n) (If (== n 0) 1 (Apply f (- n 1))))));
+        assert_eq!(fold_constants(program.clone()), program);
+        assert_eq!(fold_constants(ast!((Print (+ 1 2)))), ast!((Print 3)));
+
+        // エラーになる式は評価するときまで残す
+        assert_eq!(fold_constants(ast!((/ 1 0))), ast!((/ 1 0)));
+        assert_eq!(fold_constants(ast!((If "a" 1 2))), ast!((If "a" 1 2)));
+
+        // Define を含む分岐はスコープを保つために If のまま残す
+        let branch = ast!((If true (Define x 1) 0));
+        assert_eq!(fold_constants(branch.clone()), branch);
+        assert_eq!(
+            fold_constants(ast!((If (< 1 2) (Begin (Define y (+ 1 1)) y) 0))),
+            ast!((If true (Begin (Define y 2) y) 0))
+        );
+    }
+
+    #[test]
+    fn test_fold_constants_if_scope() {
+        let programs = [
+            ast!((Begin (If true (Define x 1) 0) x)),
+            ast!((Begin (If false 0 (Begin (Define x 2) x)) x)),
+            ast!((Begin (Define x 1) (If (== 1 1) (Begin (Define x 5) x) 0) x)),
+            ast!((Begin (Define x 1) (If true (Set! x 3) 0) x)),
+        ];
+        for program in programs {
+            assert_eq!(
+                eval(fold_constants(program.clone()), &Env::new()),
+                eval(program, &Env::new())
+            );
+        }
+    }
+
+    #[test]
+    fn test_fold_constants_same_result() {
+        let env = Env::new();
+        let sum = ast!(
+        (Define sum
+            (Func (n)
+                (If (== n (- 2 1))
+                    (* 1 1)
+                    (+ n (Apply sum (- n (+ 0 1))))))));
+        eval(fold_constants(sum), &env).unwrap();
+        assert_eq!(
+            eval(fold_constants(ast!((Apply sum (* 2 5)))), &env).unwrap(),
+            Object::Num(55)
+        );
+    }
+}
@@ -0,0 +1,689 @@
+use crate::lexer::{tokenize_with_pos, Pos, Token};
+use crate::AST;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    // 式がないまま入力が終わった
+    UnexpectedEof,
+    // 閉じられないまま入力が終わった `(` の位置
+    UnclosedParen(Pos),
+    // 対応する `(` のない `)` や、式の後ろに残ったトークン
+    UnexpectedToken(Token, Pos),
+    // #\ab のように、どのトークンとしても読めないもの
+    UnknownToken(String, Pos),
+    // 閉じの `"` がないまま入力が終わった文字列の、開きの `"` の位置
+    UnterminatedString(Pos),
+    // `(If 1 2)` のように形が正しくない特殊形式の名前と、その `(` の位置
+    InvalidForm(String, Pos),
+}
+
+// 括弧の対応だけを見た読み取り結果
+// これを AST に変換するときに If や Define などの形をチェックする
+#[derive(Debug, Clone, PartialEq)]
+enum Sexp {
+    Atom(Token),
+    // 開きの `(` の位置を持つ
+    // 'x の省略形では `'` の位置になる
+    List(Vec<Sexp>, Pos),
+}
+
+fn read(tokens: &[(Token, Pos)], pos: &mut usize) -> Result<Sexp, ParseError> {
+    let (token, at) = tokens.get(*pos).ok_or(ParseError::UnexpectedEof)?;
+    *pos += 1;
+    match token {
+        Token::LParen => {
+            let mut list = vec![];
+            loop {
+                match tokens.get(*pos) {
+                    Some((Token::RParen, _)) => {
+                        *pos += 1;
+                        return Ok(Sexp::List(list, *at));
+                    }
+                    Some(_) => list.push(read(tokens, pos)?),
+                    None => return Err(ParseError::UnclosedParen(*at)),
+                }
+            }
+        }
+        Token::RParen => Err(ParseError::UnexpectedToken(token.clone(), *at)),
+        // `#` で始まる名前は #\a のような文字のために取っておく
+        Token::Symbol(name) if name.starts_with('#') => {
+            Err(ParseError::UnknownToken(name.clone(), *at))
+        }
+        Token::Quote => Ok(Sexp::List(
+            vec![
+                Sexp::Atom(Token::Symbol("quote".to_string())),
+                read(tokens, pos)?,
+            ],
+            *at,
+        )),
+        _ => Ok(Sexp::Atom(token.clone())),
+    }
+}
+
+// エラーを知らせるために覚えておく、いま読んでいる特殊形式の名前と `(` の位置
+#[derive(Clone, Copy)]
+struct Form<'a> {
+    name: &'a str,
+    at: Pos,
+}
+
+fn invalid(form: Form) -> ParseError {
+    ParseError::InvalidForm(form.name.to_string(), form.at)
+}
+
+// Define や Func の引数などの名前の位置には識別子しか書けない
+fn symbol(sexp: &Sexp, form: Form) -> Result<String, ParseError> {
+    match sexp {
+        Sexp::Atom(Token::Symbol(name)) if name != "nil" && name != "." => Ok(name.clone()),
+        _ => Err(invalid(form)),
+    }
+}
+
+// (a b . rest) のように `.` の後ろの名前は残りの引数を受け取る
+fn params(list: &[Sexp], form: Form) -> Result<(Vec<String>, Option<String>), ParseError> {
+    let (params, rest) = match list {
+        [params @ .., Sexp::Atom(Token::Symbol(dot)), rest] if dot == "." => {
+            (params, Some(symbol(rest, form)?))
+        }
+        params => (params, None),
+    };
+    let params = params
+        .iter()
+        .map(|param| symbol(param, form))
+        .collect::<Result<_, _>>()?;
+    Ok((params, rest))
+}
+
+// Cond や Case の `(条件 式)` の節
+fn pair(sexp: &Sexp, form: Form) -> Result<(AST, AST), ParseError> {
+    match sexp {
+        Sexp::List(pair, _) if pair.len() == 2 => Ok((to_ast(&pair[0])?, to_ast(&pair[1])?)),
+        _ => Err(invalid(form)),
+    }
+}
+
+// `b:` のように `:` で終わる名前の次の式は名前付きの引数になる
+// 名前付きの引数のあとに位置で渡す引数は書けない
+fn apply(fn_lit: &Sexp, args: &[Sexp], form: Form) -> Result<AST, ParseError> {
+    let mut positional = vec![];
+    let mut named = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg {
+            Sexp::Atom(Token::Symbol(name)) if name.len() > 1 && name.ends_with(':') => {
+                let value = args.next().ok_or_else(|| invalid(form))?;
+                named.push((name[..name.len() - 1].to_string(), to_ast(value)?));
+            }
+            _ if !named.is_empty() => return Err(invalid(form)),
+            _ => positional.push(to_ast(arg)?),
+        }
+    }
+    Ok(AST::Apply {
+        fn_lit: to_boxed(fn_lit)?,
+        args: positional,
+        named,
+    })
+}
+
+fn to_boxed(sexp: &Sexp) -> Result<Box<AST>, ParseError> {
+    Ok(Box::new(to_ast(sexp)?))
+}
+
+fn atom_to_ast(token: &Token) -> AST {
+    match token {
+        Token::Num(v) => AST::Num(*v),
+        Token::Float(v) => AST::Float(*v),
+        Token::Bool(b) => AST::Bool(*b),
+        Token::Str(s) => AST::Str(s.clone()),
+        Token::Char(c) => AST::Char(*c),
+        Token::Symbol(name) if name == "nil" => AST::Nil,
+        Token::Symbol(name) => AST::Ident(name.clone()),
+        Token::LParen | Token::RParen | Token::Quote => {
+            unreachable!("parens and quotes are never read as an atom")
+        }
+    }
+}
+
+// quote の中は評価しないので、括弧はそのままリストになる
+// 中に書けない記号があれば、quote の form のエラーにする
+fn to_datum(sexp: &Sexp, form: Form) -> Result<AST, ParseError> {
+    match sexp {
+        Sexp::Atom(Token::Symbol(name)) if name != "nil" => Err(invalid(form)),
+        Sexp::Atom(atom) => Ok(atom_to_ast(atom)),
+        Sexp::List(items, _) => Ok(AST::List(
+            items
+                .iter()
+                .map(|item| to_datum(item, form))
+                .collect::<Result<_, _>>()?,
+        )),
+    }
+}
+
+fn to_ast(sexp: &Sexp) -> Result<AST, ParseError> {
+    let (list, at) = match sexp {
+        Sexp::Atom(atom) => return Ok(atom_to_ast(atom)),
+        Sexp::List(list, at) => (list, *at),
+    };
+    let (head, rest) = match list.split_first() {
+        Some((Sexp::Atom(Token::Symbol(head)), rest)) => (head.as_str(), rest),
+        _ => return Err(invalid(Form { name: "()", at })),
+    };
+    let form = Form { name: head, at };
+    let ast = match (head, rest) {
+        ("+", [left, right]) => AST::Add(to_boxed(left)?, to_boxed(right)?),
+        ("-", [left, right]) => AST::Minus(to_boxed(left)?, to_boxed(right)?),
+        ("*", [left, right]) => AST::Mul(to_boxed(left)?, to_boxed(right)?),
+        ("/", [left, right]) => AST::Div(to_boxed(left)?, to_boxed(right)?),
+        ("%", [left, right]) => AST::Mod(to_boxed(left)?, to_boxed(right)?),
+        ("==", [left, right]) => AST::Equal(to_boxed(left)?, to_boxed(right)?),
+        ("!=", [left, right]) => AST::NotEqual(to_boxed(left)?, to_boxed(right)?),
+        ("and", [left, right]) => AST::And(to_boxed(left)?, to_boxed(right)?),
+        ("or", [left, right]) => AST::Or(to_boxed(left)?, to_boxed(right)?),
+        ("not", [value]) => AST::Not(to_boxed(value)?),
+        ("neg", [value]) => AST::Negate(to_boxed(value)?),
+        ("cons", [head, tail]) => AST::Cons(to_boxed(head)?, to_boxed(tail)?),
+        ("car", [value]) => AST::Car(to_boxed(value)?),
+        ("cdr", [value]) => AST::Cdr(to_boxed(value)?),
+        ("length", [value]) => AST::Length(to_boxed(value)?),
+        ("nth", [value, index]) => AST::Nth(to_boxed(value)?, to_boxed(index)?),
+        ("first", [value]) => AST::Car(to_boxed(value)?),
+        ("rest", [value]) => AST::Cdr(to_boxed(value)?),
+        ("list", items) => AST::List(items.iter().map(to_ast).collect::<Result<_, _>>()?),
+        ("map", [func, items]) => AST::Map {
+            func: to_boxed(func)?,
+            list: to_boxed(items)?,
+        },
+        ("fold", [func, init, items]) => AST::Fold {
+            func: to_boxed(func)?,
+            init: to_boxed(init)?,
+            list: to_boxed(items)?,
+        },
+        ("<", [left, right]) => AST::Less(to_boxed(left)?, to_boxed(right)?),
+        (">", [left, right]) => AST::Greater(to_boxed(left)?, to_boxed(right)?),
+        ("<=", [left, right]) => AST::LessEq(to_boxed(left)?, to_boxed(right)?),
+        (">=", [left, right]) => AST::GreaterEq(to_boxed(left)?, to_boxed(right)?),
+        ("min", [left, right]) => AST::Min(to_boxed(left)?, to_boxed(right)?),
+        ("max", [left, right]) => AST::Max(to_boxed(left)?, to_boxed(right)?),
+        ("If", [cond, then, els]) => AST::If {
+            cond: to_boxed(cond)?,
+            then: to_boxed(then)?,
+            els: to_boxed(els)?,
+        },
+        ("Cond", [clauses @ .., default]) => AST::Cond {
+            clauses: clauses
+                .iter()
+                .map(|clause| pair(clause, form))
+                .collect::<Result<_, _>>()?,
+            default: to_boxed(default)?,
+        },
+        ("Case", [scrutinee, arms @ .., default]) => AST::Case {
+            scrutinee: to_boxed(scrutinee)?,
+            arms: arms
+                .iter()
+                .map(|arm| pair(arm, form))
+                .collect::<Result<_, _>>()?,
+            default: to_boxed(default)?,
+        },
+        ("While", [cond, body]) => AST::While {
+            cond: to_boxed(cond)?,
+            body: to_boxed(body)?,
+        },
+        ("DoTimes", [Sexp::List(binding, _), body]) if binding.len() == 2 => AST::DoTimes {
+            count: to_boxed(&binding[1])?,
+            var: symbol(&binding[0], form)?,
+            body: to_boxed(body)?,
+        },
+        ("Define", [name, value]) => AST::Define {
+            name: symbol(name, form)?,
+            value: to_boxed(value)?,
+        },
+        ("Set!", [name, value]) => AST::Set {
+            name: symbol(name, form)?,
+            value: to_boxed(value)?,
+        },
+        ("Begin", exprs) if !exprs.is_empty() => {
+            AST::Begin(exprs.iter().map(to_ast).collect::<Result<_, _>>()?)
+        }
+        ("Let", [Sexp::List(names, _), value, body]) => AST::LetList {
+            names: names
+                .iter()
+                .map(|name| symbol(name, form))
+                .collect::<Result<_, _>>()?,
+            value: to_boxed(value)?,
+            body: to_boxed(body)?,
+        },
+        ("Let", [name, value, body]) => AST::Let {
+            name: symbol(name, form)?,
+            value: to_boxed(value)?,
+            body: to_boxed(body)?,
+        },
+        ("LetRec", [Sexp::List(bindings, _), body]) => AST::LetRec {
+            bindings: bindings
+                .iter()
+                .map(|binding| match binding {
+                    Sexp::List(pair, _) if pair.len() == 2 => {
+                        Ok((symbol(&pair[0], form)?, to_ast(&pair[1])?))
+                    }
+                    _ => Err(invalid(form)),
+                })
+                .collect::<Result<_, _>>()?,
+            body: to_boxed(body)?,
+        },
+        ("Func", [Sexp::List(names, _), body @ ..]) if !body.is_empty() => {
+            let (params, rest) = params(names, form)?;
+            AST::Function {
+                params,
+                rest,
+                body: body.iter().map(to_ast).collect::<Result<_, _>>()?,
+            }
+        }
+        ("Print", [value]) => AST::Print(to_boxed(value)?),
+        ("TypeOf", [value]) => AST::TypeOf(to_boxed(value)?),
+        ("Assert", [cond, msg]) => AST::Assert {
+            cond: to_boxed(cond)?,
+            msg: to_boxed(msg)?,
+        },
+        ("quote", [datum]) => AST::Quote(Box::new(to_datum(datum, form)?)),
+        ("Apply", [fn_lit, args @ ..]) => apply(fn_lit, args, form)?,
+        ("apply-list", [fn_lit, args]) => AST::ApplyList {
+            fn_lit: to_boxed(fn_lit)?,
+            args: to_boxed(args)?,
+        },
+        _ => return Err(invalid(form)),
+    };
+    Ok(ast)
+}
+
+// `ast!` マクロと同じ書き方の文字列を AST にする
+pub fn parse(input: &str) -> Result<AST, ParseError> {
+    let tokens = tokenize_with_pos(input)?;
+    let mut pos = 0;
+    let sexp = read(&tokens, &mut pos)?;
+    if let Some((token, at)) = tokens.get(pos) {
+        return Err(ParseError::UnexpectedToken(token.clone(), *at));
+    }
+    to_ast(&sexp)
+}
+
+// Display で書いた式を parse で読み直して、元の AST に戻るかどうか
+// パーサーと Display の食い違いを見つけるためのもので、ファジングの判定にも使える
+pub fn roundtrip_ok(ast: &AST) -> bool {
+    parse(&ast.to_string()).is_ok_and(|reparsed| &reparsed == ast)
+}
+
+// 複数のトップレベルの式を順番に読む
+pub fn parse_program(input: &str) -> Result<Vec<AST>, ParseError> {
+    let tokens = tokenize_with_pos(input)?;
+    let mut pos = 0;
+    let mut forms = vec![];
+    while pos < tokens.len() {
+        let sexp = read(&tokens, &mut pos)?;
+        forms.push(to_ast(&sexp)?);
+    }
+    Ok(forms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast;
+
+    #[test]
+    fn test_parse() {
+        let pos = |line, column| Pos { line, column };
+        assert_eq!(parse("1").unwrap(), ast!(1));
+        assert_eq!(parse("-5").unwrap(), AST::Num(-5));
+        assert_eq!(parse("1.5").unwrap(), ast!(1.5));
+        assert_eq!(parse("true").unwrap(), ast!(true));
+        assert_eq!(parse("x").unwrap(), ast!(x));
+        assert_eq!(parse("nil").unwrap(), ast!(nil));
+        assert_eq!(parse("\"a b\"").unwrap(), ast!("a b"));
+        assert_eq!(parse(r"#\a").unwrap(), ast!('a'));
+        assert_eq!(parse(r"(== #\) #\ )").unwrap(), ast!((== ')' ' ')));
+
+        assert_eq!(parse("(+ 1 2)").unwrap(), ast!((+ 1 2)));
+        assert_eq!(parse("(- x 2)").unwrap(), ast!((- x 2)));
+        assert_eq!(
+            parse("  (+ (+\n(+ (+ 1 2) 3) 4)   5)  ").unwrap(),
+            ast!((+ (+ (+ (+ 1 2) 3) 4) 5))
+        );
+        assert_eq!(parse("(== 1 (* 2 3))").unwrap(), ast!((== 1 (* 2 3))));
+        assert_eq!(
+            parse("(If (<= x 1) (and true x) (not false))").unwrap(),
+            ast!((If (<= x 1) (and true x) (not false)))
+        );
+        assert_eq!(parse("(neg (+ x 1))").unwrap(), ast!((neg (+ x 1))));
+        assert_eq!(
+            parse("(Cond ((== x 1) 10) ((== x 2) 20) 30)").unwrap(),
+            ast!((Cond ((== x 1) 10) ((== x 2) 20) 30))
+        );
+        assert_eq!(
+            parse("(Case (car x) (1 10) (\"a\" 20) 30)").unwrap(),
+            ast!((Case (car x) (1 10) ("a" 20) 30))
+        );
+        assert_eq!(parse("(Case x 1)").unwrap(), ast!((Case x 1)));
+        assert_eq!(
+            parse("(Define f (Func (a b) (+ a b)))").unwrap(),
+            ast!((Define f (Func (a b) (+ a b))))
+        );
+        assert_eq!(
+            parse("(Func () (Set! x 1) x)").unwrap(),
+            ast!((Func () (Set! x 1) x))
+        );
+        assert_eq!(
+            parse("(Let x 1 (Begin (Define y x) y))").unwrap(),
+            ast!((Let x 1 (Begin (Define y x) y)))
+        );
+        assert_eq!(
+            parse("(Let (a b) (list 1 2) (+ a b))").unwrap(),
+            ast!((Let (a b) (list 1 2) (+ a b)))
+        );
+        assert_eq!(
+            parse("(Apply (Func (a) a) 10)").unwrap(),
+            ast!((Apply (Func (a) a) 10))
+        );
+        assert_eq!(parse("(Apply f)").unwrap(), ast!((Apply f)));
+        assert_eq!(
+            parse("(Func (a b . rest) rest)").unwrap(),
+            ast!((Func (a b . rest) rest))
+        );
+        assert_eq!(
+            parse("(Func (. rest) rest)").unwrap(),
+            ast!((Func (. rest) rest))
+        );
+        assert_eq!(
+            parse("(While (> x 0) (Set! x (- x 1)))").unwrap(),
+            ast!((While (> x 0) (Set! x (- x 1))))
+        );
+        assert_eq!(
+            parse("(LetRec ((f (Func () (Apply g))) (g (Func () 1))) (Apply f))").unwrap(),
+            ast!((LetRec ((f (Func () (Apply g))) (g (Func () 1))) (Apply f)))
+        );
+        assert_eq!(parse("(LetRec () 1)").unwrap(), ast!((LetRec () 1)));
+        assert_eq!(
+            parse("(DoTimes (i (+ n 1)) (Print i))").unwrap(),
+            ast!((DoTimes (i (+ n 1)) (Print i)))
+        );
+        assert_eq!(
+            parse("(DoTimes (3 i) i)"),
+            Err(ParseError::InvalidForm("DoTimes".to_string(), pos(1, 1)))
+        );
+        assert_eq!(parse("(Print (+ 1 2))").unwrap(), ast!((Print (+ 1 2))));
+        assert_eq!(parse("(TypeOf (car x))").unwrap(), ast!((TypeOf (car x))));
+        assert_eq!(
+            parse("(Assert (< i 3) \"i < 3\")").unwrap(),
+            ast!((Assert (< i 3) "i < 3"))
+        );
+        assert_eq!(parse("(list 1 (+ x 1))").unwrap(), ast!((list 1 (+ x 1))));
+        assert_eq!(parse("(list)").unwrap(), ast!((list)));
+        assert_eq!(
+            parse("(length '(1 2))").unwrap(),
+            ast!((length (quote (1 2))))
+        );
+        assert_eq!(parse("(nth x (+ i 1))").unwrap(), ast!((nth x (+ i 1))));
+        assert_eq!(parse("(!= x \"a\")").unwrap(), ast!((!= x "a")));
+        assert_eq!(
+            parse("(Apply f 1 b: (+ x 1) a: 2)").unwrap(),
+            ast!((Apply f 1 b: (+ x 1) a: 2))
+        );
+        assert_eq!(
+            parse("(Apply f a: 1 2)"),
+            Err(ParseError::InvalidForm("Apply".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(Apply f a:)"),
+            Err(ParseError::InvalidForm("Apply".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(apply-list f '(1 2))").unwrap(),
+            ast!((apply-list f (quote (1 2))))
+        );
+        assert_eq!(parse("(first (rest x))").unwrap(), ast!((car (cdr x))));
+        assert_eq!(
+            parse("(map (Func (x) x) (list 1))").unwrap(),
+            ast!((map (Func (x) x) (list 1)))
+        );
+        assert_eq!(
+            parse("(fold f 0 '(1))").unwrap(),
+            ast!((fold f 0 (quote (1))))
+        );
+        assert_eq!(parse("(min 1 (max x 2))").unwrap(), ast!((min 1 (max x 2))));
+        // 演算子の位置でなければ prelude の関数の名前として読む
+        assert_eq!(parse("(Apply min 1 2)").unwrap(), ast!((Apply min 1 2)));
+        assert_eq!(
+            parse("(cons (car x) (cdr nil))").unwrap(),
+            ast!((cons (car x) (cdr nil)))
+        );
+        assert_eq!(
+            parse("(quote (1 (true \"a\") ()))").unwrap(),
+            ast!((quote (1 (true "a") ())))
+        );
+        assert_eq!(parse("'(1 2 3)").unwrap(), ast!((quote (1 2 3))));
+        assert_eq!(
+            parse("(car '(nil 2))").unwrap(),
+            ast!((car (quote (nil 2))))
+        );
+
+        assert_eq!(parse("(+ 1 2) ; add them").unwrap(), ast!((+ 1 2)));
+        assert_eq!(
+            parse(
+                "(Define f ; a function\n\
+                   (Func (x) ; takes x\n\
+                     (+ x 1)))"
+            )
+            .unwrap(),
+            ast!((Define f (Func (x) (+ x 1))))
+        );
+    }
+
+    #[test]
+    fn test_parse_program() {
+        let program = "
+            (Define sum (Func (n) (If (== n 1) 1 (+ n (Apply sum (- n 1))))))
+            (Apply sum 5)
+        ";
+        assert_eq!(
+            parse_program(program).unwrap(),
+            vec![
+                ast!((Define sum (Func (n) (If (== n 1) 1 (+ n (Apply sum (- n 1))))))),
+                ast!((Apply sum 5)),
+            ]
+        );
+        assert_eq!(parse_program("1 x").unwrap(), vec![ast!(1), ast!(x)]);
+        assert_eq!(parse_program("").unwrap(), vec![]);
+        assert_eq!(parse_program("  ; nothing\n").unwrap(), vec![]);
+
+        assert_eq!(
+            parse_program("(+ 1 2) (- 1"),
+            Err(ParseError::UnclosedParen(Pos { line: 1, column: 9 }))
+        );
+        assert_eq!(
+            parse_program("(+ 1 2)\n  (- 1 2))"),
+            Err(ParseError::UnexpectedToken(
+                Token::RParen,
+                Pos {
+                    line: 2,
+                    column: 10
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_roundtrip_ok() {
+        let asts = [
+            ast!(1),
+            ast!((- 0 2.5)),
+            ast!("say \"hi\"\n"),
+            ast!(' '),
+            ast!((Define f (Func (a b . rest) (Print rest) (+ a b)))),
+            ast!((Apply (Func (x) (Apply (Func (y) (* x y)) 3)) 2)),
+            ast!((Apply f 1 b: (Apply g 2))),
+            ast!((Cond ((== x 1) "one") ((!= x 2) (list x x)) nil)),
+            ast!((LetRec ((even (Func (n) (If (== n 0) true (Apply odd (- n 1)))))
+                          (odd (Func (n) (If (== n 0) false (Apply even (- n 1))))))
+                    (Apply even 10))),
+            ast!((Let (a b) (list 1 2) (Assert (< a b) "a < b"))),
+            ast!((DoTimes (i 3) (Print (neg i)))),
+            ast!((quote (1 (true "b") 'c'))),
+            ast!((fold (Func (acc x) (+ acc x)) 0 (map (Func (x) (* x x)) xs))),
+        ];
+        for ast in &asts {
+            assert!(roundtrip_ok(ast), "{} does not round trip", ast);
+        }
+
+        // Display は lexer が読めるエスケープだけを使うので、制御文字を含む文字列も戻る
+        for s in [
+            "a\rb",
+            "\0",
+            "\u{1b}[31mred\u{1b}[0m",
+            "\u{7f}\u{85}",
+            "\\n is not a newline",
+        ] {
+            let ast = AST::Apply {
+                fn_lit: Box::new(AST::Ident("f".to_string())),
+                args: vec![
+                    AST::Str(s.to_string()),
+                    AST::Char(s.chars().next().unwrap()),
+                ],
+                named: vec![],
+            };
+            assert!(roundtrip_ok(&ast), "{:?} does not round trip", s);
+        }
+
+        // 空白を含む名前は Display すると二つの名前に分かれてしまう
+        assert!(!roundtrip_ok(&AST::Ident("a b".to_string())));
+        assert!(!roundtrip_ok(&AST::Float(f64::NAN)));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let pos = |line, column| Pos { line, column };
+        assert_eq!(parse(""), Err(ParseError::UnexpectedEof));
+        assert_eq!(parse("(+ 1 2"), Err(ParseError::UnclosedParen(pos(1, 1))));
+        assert_eq!(
+            parse("(If (< x 1)\n  (+ x 1\n  x)"),
+            Err(ParseError::UnclosedParen(pos(1, 1)))
+        );
+        // 閉じられていない一番内側の `(` を指す
+        assert_eq!(
+            parse("(If (< x 1) (+ x (- 1 2) x"),
+            Err(ParseError::UnclosedParen(pos(1, 13)))
+        );
+        assert_eq!(
+            parse("(+ (- 1 2) (* 3"),
+            Err(ParseError::UnclosedParen(pos(1, 12)))
+        );
+        assert_eq!(
+            parse(")"),
+            Err(ParseError::UnexpectedToken(Token::RParen, pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(+ 1 2))"),
+            Err(ParseError::UnexpectedToken(Token::RParen, pos(1, 8)))
+        );
+        assert_eq!(
+            parse("1 2"),
+            Err(ParseError::UnexpectedToken(Token::Num(2), pos(1, 3)))
+        );
+        assert_eq!(
+            parse("(== c\n    #\\ab)"),
+            Err(ParseError::UnknownToken("#\\ab".to_string(), pos(2, 5)))
+        );
+        assert_eq!(
+            parse("#t"),
+            Err(ParseError::UnknownToken("#t".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(If 1 2)"),
+            Err(ParseError::InvalidForm("If".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(Define 1 2)"),
+            Err(ParseError::InvalidForm("Define".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(Define nil 1)"),
+            Err(ParseError::InvalidForm("Define".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(Func (x))"),
+            Err(ParseError::InvalidForm("Func".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(Func (a . b c) a)"),
+            Err(ParseError::InvalidForm("Func".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(Func (a .) a)"),
+            Err(ParseError::InvalidForm("Func".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(Case x (1) 2)"),
+            Err(ParseError::InvalidForm("Case".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(Case x)"),
+            Err(ParseError::InvalidForm("Case".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("(LetRec ((a)) a)"),
+            Err(ParseError::InvalidForm("LetRec".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("()"),
+            Err(ParseError::InvalidForm("()".to_string(), pos(1, 1)))
+        );
+        assert_eq!(
+            parse("'(1 x)"),
+            Err(ParseError::InvalidForm("quote".to_string(), pos(1, 1)))
+        );
+        assert_eq!(parse("'"), Err(ParseError::UnexpectedEof));
+        // 位置は外側の式ではなく、形の正しくない式の `(` や `'` の位置になる
+        assert_eq!(
+            parse("(Begin 1\n  (If 1 2))"),
+            Err(ParseError::InvalidForm("If".to_string(), pos(2, 3)))
+        );
+        assert_eq!(
+            parse("(list 1 '(2 (x)))"),
+            Err(ParseError::InvalidForm("quote".to_string(), pos(1, 9)))
+        );
+        assert_eq!(
+            parse_program("(Define x 1)\n(Cond (1) 2)"),
+            Err(ParseError::InvalidForm("Cond".to_string(), pos(2, 1)))
+        );
+        assert_eq!(
+            parse("(Print (+ 1 ( )))"),
+            Err(ParseError::InvalidForm("()".to_string(), pos(1, 13)))
+        );
+        assert_eq!(
+            parse("(Print \"abc)"),
+            Err(ParseError::UnterminatedString(pos(1, 8)))
+        );
+        assert_eq!(
+            parse_program("(Print 1)\n(Print \"a\\"),
+            Err(ParseError::UnterminatedString(pos(2, 8)))
+        );
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        let message = |input: &str| parse(input).unwrap_err().to_string();
+        assert_eq!(message("(+ 1 2))"), "unexpected ')' at 1:8");
+        assert_eq!(
+            message("(Print\n  \"a\") \"b\""),
+            "unexpected '\"b\"' at 2:8"
+        );
+        assert_eq!(message("(+ 1\n  (- 2"), "unclosed '(' at 2:3");
+        assert_eq!(message("(list #foo)"), "unknown token '#foo' at 1:7");
+        assert_eq!(message("(Print \"a)"), "unterminated string at 1:8");
+        assert_eq!(message(""), "unexpected end of input");
+        assert_eq!(message("(If 1 2)"), "invalid form: If at 1:1");
+        assert_eq!(
+            message("(- 1\n  (Define 2 3))"),
+            "invalid form: Define at 2:3"
+        );
+        let err: Box<dyn std::error::Error> = Box::new(parse(")").unwrap_err());
+        assert_eq!(err.to_string(), "unexpected ')' at 1:1");
+    }
+}
@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+
+use crate::{elements, list, maximum, minimum, truthy, EvalError, NativeFn, Object};
+
+fn arity(expected: usize, args: &[Object]) -> Result<(), EvalError> {
+    if args.len() != expected {
+        return Err(EvalError::Arity {
+            expected,
+            got: args.len(),
+        });
+    }
+    Ok(())
+}
+
+fn abs(args: Vec<Object>) -> Result<Object, EvalError> {
+    arity(1, &args)?;
+    match &args[0] {
+        Object::Num(v) => v.checked_abs().map(Object::Num).ok_or(EvalError::Overflow),
+        Object::Float(v) => Ok(Object::Float(v.abs())),
+        obj => Err(EvalError::TypeMismatch(format!(
+            "abs is expected to take Num or Float, but got {:?}",
+            obj
+        ))),
+    }
+}
+
+// 比べられない組み合わせなら TypeMismatch になる
+fn min(args: Vec<Object>) -> Result<Object, EvalError> {
+    arity(2, &args)?;
+    let mut args = args.into_iter();
+    minimum(args.next().unwrap(), args.next().unwrap())
+}
+
+fn max(args: Vec<Object>) -> Result<Object, EvalError> {
+    arity(2, &args)?;
+    let mut args = args.into_iter();
+    maximum(args.next().unwrap(), args.next().unwrap())
+}
+
+// 0 と比べた結果
+// Float の NaN はどれにも当てはまらないので None になる
+fn sign(name: &str, args: Vec<Object>) -> Result<Option<std::cmp::Ordering>, EvalError> {
+    arity(1, &args)?;
+    match &args[0] {
+        Object::Num(v) => Ok(Some(v.cmp(&0))),
+        Object::Float(v) => Ok(v.partial_cmp(&0.0)),
+        obj => Err(EvalError::TypeMismatch(format!(
+            "{} is expected to take Num or Float, but got {:?}",
+            name, obj
+        ))),
+    }
+}
+
+fn is_zero(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Bool(
+        sign("zero?", args)?.is_some_and(|o| o.is_eq()),
+    ))
+}
+
+fn is_positive(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Bool(
+        sign("positive?", args)?.is_some_and(|o| o.is_gt()),
+    ))
+}
+
+fn is_negative(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Bool(
+        sign("negative?", args)?.is_some_and(|o| o.is_lt()),
+    ))
+}
+
+// quotient と remainder が受け取る二つの Num
+fn integers(name: &str, args: Vec<Object>) -> Result<(i64, i64), EvalError> {
+    arity(2, &args)?;
+    match (&args[0], &args[1]) {
+        (Object::Num(_), Object::Num(0)) => Err(EvalError::DivByZero),
+        (Object::Num(left), Object::Num(right)) => Ok((*left, *right)),
+        (left, right) => Err(EvalError::TypeMismatch(format!(
+            "{} is expected to take Num, but got left: {:?}, right: {:?}",
+            name, left, right
+        ))),
+    }
+}
+
+// Scheme と同じく商は 0 の方向に切り捨てる
+// (quotient -7 2) は -3 で、-4 にはならない
+fn quotient(args: Vec<Object>) -> Result<Object, EvalError> {
+    let (left, right) = integers("quotient", args)?;
+    left.checked_div(right)
+        .map(Object::Num)
+        .ok_or(EvalError::Overflow)
+}
+
+// 余りの符号は割られる数と同じになり、(+ (* q right) r) が left に戻る
+// (remainder -7 2) は -1、(remainder 7 -2) は 1
+fn remainder(args: Vec<Object>) -> Result<Object, EvalError> {
+    let (left, right) = integers("remainder", args)?;
+    left.checked_rem(right)
+        .map(Object::Num)
+        .ok_or(EvalError::Overflow)
+}
+
+// 数値のリストを小さい順に並べた新しいリストを返す
+// 順序は < と同じで、等しい要素は元の順番のまま残る
+fn sort(args: Vec<Object>) -> Result<Object, EvalError> {
+    arity(1, &args)?;
+    let mut items = elements(args.into_iter().next().unwrap(), "sort")?;
+    if let Some(item) = items
+        .iter()
+        .find(|item| !matches!(item, Object::Num(_) | Object::Float(_)))
+    {
+        return Err(EvalError::TypeMismatch(format!(
+            "sort is expected to take a List of Num or Float, but got {:?}",
+            item
+        )));
+    }
+    // Num と Float はどれどうしでも比べられる
+    items.sort_by(|left, right| left.partial_cmp(right).unwrap());
+    Ok(list(items))
+}
+
+// 引数の種類を調べる num? などが受け取る一つの値
+fn one(args: Vec<Object>) -> Result<Object, EvalError> {
+    arity(1, &args)?;
+    Ok(args.into_iter().next().unwrap())
+}
+
+fn is_num(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Bool(matches!(one(args)?, Object::Num(_))))
+}
+
+fn is_bool(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Bool(matches!(one(args)?, Object::Bool(_))))
+}
+
+fn is_str(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Bool(matches!(one(args)?, Object::Str(_))))
+}
+
+// 組み込み関数も関数として扱う
+fn is_func(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Bool(matches!(
+        one(args)?,
+        Object::Function { .. } | Object::Native { .. }
+    )))
+}
+
+// nil は空のリストなので真になり、cons で作ったペアは偽になる
+fn is_list(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Bool(matches!(
+        one(args)?,
+        Object::List(_) | Object::Nil
+    )))
+}
+
+// If で真偽値として扱うときと同じ変換を明示的に行う
+fn bool_to_num(args: Vec<Object>) -> Result<Object, EvalError> {
+    arity(1, &args)?;
+    let b = bool::try_from(args.into_iter().next().unwrap())?;
+    Ok(Object::Num(b as i64))
+}
+
+fn num_to_bool(args: Vec<Object>) -> Result<Object, EvalError> {
+    arity(1, &args)?;
+    let v = i64::try_from(args.into_iter().next().unwrap())?;
+    Ok(Object::Bool(v != 0))
+}
+
+// 引数をそれぞれ Display の形にしてつなげる
+// 文字列は引用符をつけずにそのままつながる
+fn str_concat(args: Vec<Object>) -> Result<Object, EvalError> {
+    Ok(Object::Str(
+        args.iter().map(|arg| arg.to_string()).collect(),
+    ))
+}
+
+fn not(args: Vec<Object>) -> Result<Object, EvalError> {
+    arity(1, &args)?;
+    let value = args.into_iter().next().unwrap();
+    Ok(Object::Bool(!truthy(value)?))
+}
+
+// 組み込み関数をはじめから束縛した環境の中身
+// Env::from(prelude()) で評価に使える
+pub fn prelude() -> HashMap<String, Object> {
+    let builtins: [(&str, NativeFn); 18] = [
+        ("abs", abs),
+        ("min", min),
+        ("max", max),
+        ("not", not),
+        ("zero?", is_zero),
+        ("positive?", is_positive),
+        ("negative?", is_negative),
+        ("bool->num", bool_to_num),
+        ("num->bool", num_to_bool),
+        ("str-concat", str_concat),
+        ("quotient", quotient),
+        ("remainder", remainder),
+        ("sort", sort),
+        ("num?", is_num),
+        ("bool?", is_bool),
+        ("str?", is_str),
+        ("func?", is_func),
+        ("list?", is_list),
+    ];
+    builtins
+        .into_iter()
+        .map(|(name, func)| {
+            (
+                name.to_string(),
+                Object::Native {
+                    name: name.to_string(),
+                    func,
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::{ast, eval, Env};
+
+    #[test]
+    fn test_prelude() {
+        let env = Env::from(prelude());
+        assert_eq!(
+            eval(ast!((Apply abs (- 0 5))), &env).unwrap(),
+            Object::Num(5)
+        );
+        assert_eq!(
+            eval(ast!((Apply abs (- 0 1.5))), &env).unwrap(),
+            Object::Float(1.5)
+        );
+        assert_eq!(eval(ast!((Apply min 3 2)), &env).unwrap(), Object::Num(2));
+        assert_eq!(eval(ast!((Apply max 3 2)), &env).unwrap(), Object::Num(3));
+        assert_eq!(
+            eval(ast!((Apply max "a" "b")), &env).unwrap(),
+            Object::Str("b".to_string())
+        );
+        assert_eq!(
+            eval(ast!((Apply not nil)), &env).unwrap(),
+            Object::Bool(true)
+        );
+        // 関数として他の関数に渡せる
+        assert_eq!(
+            eval(ast!((Apply (Func (f) (Apply f 1 2)) max)), &env).unwrap(),
+            Object::Num(2)
+        );
+    }
+
+    #[test]
+    fn test_prelude_predicates() {
+        let env = Env::from(prelude());
+        let cases = [
+            (ast!(5), [false, true, false]),
+            (ast!(0), [true, false, false]),
+            (ast!((- 0 3)), [false, false, true]),
+            (ast!(0.5), [false, true, false]),
+            (ast!(0.0), [true, false, false]),
+            (ast!((- 0 0.5)), [false, false, true]),
+        ];
+        // ? は ast! に書けないので parse で読む
+        let run = |source: &str| eval(parse(source).unwrap(), &env);
+        for (value, [zero, positive, negative]) in cases {
+            env.define("v".to_string(), eval(value, &env).unwrap());
+            assert_eq!(run("(Apply zero? v)").unwrap(), Object::Bool(zero));
+            assert_eq!(run("(Apply positive? v)").unwrap(), Object::Bool(positive));
+            assert_eq!(run("(Apply negative? v)").unwrap(), Object::Bool(negative));
+        }
+        assert_eq!(
+            run("(If (Apply zero? 0) \"zero\" \"other\")").unwrap(),
+            Object::Str("zero".to_string())
+        );
+        for name in ["zero?", "positive?", "negative?"] {
+            assert!(matches!(
+                run(&format!("(Apply {} \"1\")", name)),
+                Err(EvalError::TypeMismatch(_))
+            ));
+            assert_eq!(
+                run(&format!("(Apply {} 1 2)", name)),
+                Err(EvalError::Arity {
+                    expected: 1,
+                    got: 2
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_prelude_coercion() {
+        let env = Env::from(prelude());
+        assert_eq!(eval(ast!((bool->num true)), &env).unwrap(), Object::Num(1));
+        assert_eq!(eval(ast!((bool->num false)), &env).unwrap(), Object::Num(0));
+        assert_eq!(
+            eval(ast!((+ (bool->num (< 1 2)) (bool->num (< 2 1)))), &env).unwrap(),
+            Object::Num(1)
+        );
+        assert_eq!(
+            eval(ast!((num->bool 0)), &env).unwrap(),
+            Object::Bool(false)
+        );
+        assert_eq!(eval(ast!((num->bool 5)), &env).unwrap(), Object::Bool(true));
+        assert_eq!(
+            eval(ast!((num->bool (- 0 1))), &env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(
+            eval(ast!((num->bool (bool->num true))), &env).unwrap(),
+            Object::Bool(true)
+        );
+        assert!(matches!(
+            eval(ast!((bool->num 1)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval(ast!((num->bool 1.0)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_prelude_str_concat() {
+        let env = Env::from(prelude());
+        let run = |source: &str| eval(parse(source).unwrap(), &env).unwrap();
+        assert_eq!(
+            run("(Apply str-concat \"x = \" 5)"),
+            Object::Str("x = 5".to_string())
+        );
+        assert_eq!(
+            run("(Apply str-concat 1.0 #\\a nil (list 1 \"b\") true)"),
+            Object::Str("1.0anil(1 b)true".to_string())
+        );
+        assert_eq!(run("(Apply str-concat)"), Object::Str(String::new()));
+        assert_eq!(
+            run("(apply-list str-concat (list \"a\" 1 \"b\"))"),
+            Object::Str("a1b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_prelude_quotient_remainder() {
+        let env = Env::from(prelude());
+        let cases = [
+            ((7, 2), (3, 1)),
+            ((-7, 2), (-3, -1)),
+            ((7, -2), (-3, 1)),
+            ((-7, -2), (3, -1)),
+            ((6, 3), (2, 0)),
+            ((-6, 3), (-2, 0)),
+            ((1, 5), (0, 1)),
+            ((-1, 5), (0, -1)),
+        ];
+        for ((left, right), (q, r)) in cases {
+            env.define("a".to_string(), Object::Num(left));
+            env.define("b".to_string(), Object::Num(right));
+            assert_eq!(
+                eval(ast!((Apply quotient a b)), &env).unwrap(),
+                Object::Num(q)
+            );
+            assert_eq!(
+                eval(ast!((Apply remainder a b)), &env).unwrap(),
+                Object::Num(r)
+            );
+            // 商と余りから元の数に戻る
+            assert_eq!(
+                eval(
+                    ast!((+ (* (Apply quotient a b) b) (Apply remainder a b))),
+                    &env
+                )
+                .unwrap(),
+                Object::Num(left)
+            );
+        }
+
+        assert_eq!(
+            eval(ast!((Apply quotient 1 0)), &env),
+            Err(EvalError::DivByZero)
+        );
+        assert_eq!(
+            eval(ast!((Apply remainder 1 0)), &env),
+            Err(EvalError::DivByZero)
+        );
+        env.define("min".to_string(), Object::Num(i64::MIN));
+        assert_eq!(
+            eval(ast!((Apply quotient min (- 0 1))), &env),
+            Err(EvalError::Overflow)
+        );
+        assert!(matches!(
+            eval(ast!((Apply quotient 7.0 2)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(
+            eval(ast!((Apply remainder 7)), &env),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_prelude_sort() {
+        let env = Env::from(prelude());
+        assert_eq!(
+            eval(ast!((Apply sort (list 3 1 2))), &env).unwrap(),
+            Object::List(vec![Object::Num(1), Object::Num(2), Object::Num(3)])
+        );
+        assert_eq!(
+            eval(ast!((Apply sort (list 2.5 (- 0 1) 2 0.5))), &env).unwrap(),
+            Object::List(vec![
+                Object::Num(-1),
+                Object::Float(0.5),
+                Object::Num(2),
+                Object::Float(2.5)
+            ])
+        );
+        assert_eq!(eval(ast!((Apply sort nil)), &env).unwrap(), Object::Nil);
+
+        // 2^53 を超える Num も f64 に丸めずに Float と比べる
+        let big = 1i64 << 60;
+        let items = vec![
+            Object::Float(big as f64),
+            Object::Num(big + 1),
+            Object::Num(big - 1),
+            Object::Num(big),
+            Object::Num(i64::MAX),
+            Object::Float(1e19),
+            Object::Float(-1e19),
+            Object::Num(i64::MIN),
+            Object::Float(2.5),
+            Object::Num(2),
+            Object::Float(2.0),
+            Object::Float(-0.0),
+            Object::Num(0),
+            Object::Float(0.0),
+        ];
+        env.define("xs".to_string(), Object::List(items));
+        assert_eq!(
+            eval(ast!((Apply sort xs)), &env).unwrap(),
+            Object::List(vec![
+                Object::Float(-1e19),
+                Object::Num(i64::MIN),
+                Object::Num(0),
+                Object::Float(-0.0),
+                Object::Float(0.0),
+                Object::Num(2),
+                Object::Float(2.0),
+                Object::Float(2.5),
+                Object::Num(big - 1),
+                Object::Num(big),
+                Object::Float(big as f64),
+                Object::Num(big + 1),
+                Object::Num(i64::MAX),
+                Object::Float(1e19),
+            ])
+        );
+        // 元のリストは変わらない
+        eval(ast!((Define xs (list 2 1))), &env).unwrap();
+        eval(ast!((Apply sort xs)), &env).unwrap();
+        assert_eq!(
+            env.get("xs"),
+            Some(Object::List(vec![Object::Num(2), Object::Num(1)]))
+        );
+
+        assert!(matches!(
+            eval(ast!((Apply sort (list 3 "a" 1))), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval(ast!((Apply sort 3)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_prelude_type_predicates() {
+        let env = Env::from(prelude());
+        assert_eq!(eval(ast!((num? 5)), &env).unwrap(), Object::Bool(true));
+        assert_eq!(eval(ast!((num? true)), &env).unwrap(), Object::Bool(false));
+
+        let values = [
+            ast!(5),
+            ast!(1.5),
+            ast!(true),
+            ast!("a"),
+            ast!((Func (x) x)),
+            ast!(abs),
+            ast!((list 1 2)),
+            ast!(nil),
+            ast!((cons 1 2)),
+        ];
+        let expected = [
+            // num? bool? str? func? list?
+            [true, false, false, false, false],
+            [false, false, false, false, false],
+            [false, true, false, false, false],
+            [false, false, true, false, false],
+            [false, false, false, true, false],
+            [false, false, false, true, false],
+            [false, false, false, false, true],
+            [false, false, false, false, true],
+            [false, false, false, false, false],
+        ];
+        for (value, [num, boolean, string, func, list]) in values.into_iter().zip(expected) {
+            env.define("v".to_string(), eval(value, &env).unwrap());
+            assert_eq!(eval(ast!((num? v)), &env).unwrap(), Object::Bool(num));
+            assert_eq!(eval(ast!((bool? v)), &env).unwrap(), Object::Bool(boolean));
+            assert_eq!(eval(ast!((str? v)), &env).unwrap(), Object::Bool(string));
+            assert_eq!(eval(ast!((func? v)), &env).unwrap(), Object::Bool(func));
+            assert_eq!(eval(ast!((list? v)), &env).unwrap(), Object::Bool(list));
+        }
+
+        // 引数を評価してから調べる
+        assert_eq!(
+            eval(ast!((num? (+ 1 2))), &env).unwrap(),
+            Object::Bool(true)
+        );
+        assert_eq!(eval(ast!((If (str? v) 1 2)), &env).unwrap(), Object::Num(2));
+        assert_eq!(
+            eval(parse("(Apply list? 1 2)").unwrap(), &env),
+            Err(EvalError::Arity {
+                expected: 1,
+                got: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_prelude_error() {
+        let env = Env::from(prelude());
+        assert!(matches!(
+            eval(ast!((Apply abs true)), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert!(matches!(
+            eval(ast!((Apply min 1 "a")), &env),
+            Err(EvalError::TypeMismatch(_))
+        ));
+        assert_eq!(
+            eval(ast!((Apply max 1)), &env),
+            Err(EvalError::Arity {
+                expected: 2,
+                got: 1
+            })
+        );
+        env.define("smallest".to_string(), Object::Num(i64::MIN));
+        assert_eq!(
+            eval(ast!((Apply abs smallest)), &env),
+            Err(EvalError::Overflow)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::AST;
+
+// Display の出力を括弧の構造で分けたもの
+// 改行する位置を決めるためだけに使うので、アトムは文字列のまま持つ
+enum Node {
+    Atom(String),
+    List(Vec<Node>),
+}
+
+// 文字列と #\c の中の括弧や空白はアトムの一部として読む
+fn read_atom(chars: &mut Peekable<Chars>) -> String {
+    let mut atom = String::new();
+    if chars.peek() == Some(&'"') {
+        atom.push(chars.next().unwrap());
+        while let Some(c) = chars.next() {
+            atom.push(c);
+            match c {
+                '"' => break,
+                '\\' => atom.extend(chars.next()),
+                _ => {}
+            }
+        }
+        return atom;
+    }
+    while let Some(&c) = chars.peek() {
+        if atom == "#\\" {
+            atom.push(c);
+            chars.next();
+            continue;
+        }
+        if c == '(' || c == ')' || c.is_whitespace() {
+            break;
+        }
+        atom.push(c);
+        chars.next();
+    }
+    atom
+}
+
+fn read(chars: &mut Peekable<Chars>) -> Node {
+    if chars.peek() != Some(&'(') {
+        return Node::Atom(read_atom(chars));
+    }
+    chars.next();
+    let mut items = vec![];
+    loop {
+        match chars.peek() {
+            Some(')') | None => {
+                chars.next();
+                return Node::List(items);
+            }
+            Some(c) if c.is_whitespace() => {
+                chars.next();
+            }
+            Some(_) => items.push(read(chars)),
+        }
+    }
+}
+
+fn flat(node: &Node) -> String {
+    match node {
+        Node::Atom(atom) => atom.clone(),
+        Node::List(items) => {
+            let items: Vec<String> = items.iter().map(flat).collect();
+            format!("({})", items.join(" "))
+        }
+    }
+}
+
+// indent は node を書き始める列
+// 収まらないリストは (If cond のように演算子と最初の引数だけ同じ行に書き、
+// 残りを一つずつ 2 つ深く字下げした行に書く
+fn layout(node: &Node, indent: usize, width: usize, out: &mut String) {
+    let line = flat(node);
+    let items = match node {
+        Node::List(items) if indent + line.chars().count() > width && !items.is_empty() => items,
+        _ => {
+            out.push_str(&line);
+            return;
+        }
+    };
+    out.push('(');
+    layout(&items[0], indent + 1, width, out);
+    let mut rest = &items[1..];
+    if let (Node::Atom(head), Some((first, others))) = (&items[0], rest.split_first()) {
+        out.push(' ');
+        layout(first, indent + head.chars().count() + 2, width, out);
+        rest = others;
+    }
+    for item in rest {
+        out.push('\n');
+        out.push_str(&" ".repeat(indent + 2));
+        layout(item, indent + 2, width, out);
+    }
+    out.push(')');
+}
+
+// 一行が width に収まらない式を改行と字下げで複数行に分けて書く
+// 収まる部分は Display と同じ一行のまま書くので、parser::parse で読み直せば元の AST に戻る
+pub fn pretty(ast: &AST, width: usize) -> String {
+    let mut out = String::new();
+    layout(
+        &read(&mut ast.to_string().chars().peekable()),
+        0,
+        width,
+        &mut out,
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast, parser};
+
+    #[test]
+    fn test_pretty() {
+        let program = ast!(
+        (Define f
+            (Func (n)
+                (If (< n 1)
+                    0
+                    (If (< n 10)
+                        (+ n (Apply f (- n 1)))
+                        (If (< n 100) (* n 2) n))))));
+        assert_eq!(
+            pretty(&program, 40),
+            "\
+(Define f
+  (Func (n)
+    (If (< n 1)
+      0
+      (If (< n 10)
+        (+ n (Apply f (- n 1)))
+        (If (< n 100) (* n 2) n)))))"
+        );
+        assert_eq!(parser::parse(&pretty(&program, 40)).unwrap(), program);
+
+        // 収まるなら Display と同じ
+        assert_eq!(pretty(&program, 200), program.to_string());
+        assert_eq!(pretty(&ast!(x), 0), "x");
+    }
+
+    #[test]
+    fn test_pretty_atoms() {
+        let program = ast!((Cond ((== x "a (b)") '(') ((== x "\"") ' ') (quote (1 ()))));
+        let printed = pretty(&program, 30);
+        assert_eq!(
+            printed,
+            "\
+(Cond ((== x \"a (b)\") #\\()
+  ((== x \"\\\"\") #\\ )
+  (quote (1 ())))"
+        );
+        assert_eq!(parser::parse(&printed).unwrap(), program);
+    }
+}
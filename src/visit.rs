@@ -0,0 +1,383 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::AST;
+
+static GENSYM_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// AST を書き換えるときに、プログラム中の名前とぶつからない新しい名前を作る
+// `__` を含む識別子を使わなければ衝突しない
+pub fn gensym(prefix: &str) -> String {
+    let n = GENSYM_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{}__{}", prefix, n)
+}
+
+fn map_boxed(ast: AST, f: &mut impl FnMut(AST) -> AST) -> Box<AST> {
+    Box::new(map_ast(ast, f))
+}
+
+fn map_all(asts: Vec<AST>, f: &mut impl FnMut(AST) -> AST) -> Vec<AST> {
+    asts.into_iter().map(|ast| map_ast(ast, f)).collect()
+}
+
+// 子から順に f を適用して木を組み立て直す
+// f に渡されるノードの子はすでに f を適用したあとのものになっている
+// quote の中はコードではなくデータなのでたどらない
+pub fn map_ast(ast: AST, f: &mut impl FnMut(AST) -> AST) -> AST {
+    let ast = match ast {
+        AST::Num(_)
+        | AST::Float(_)
+        | AST::Bool(_)
+        | AST::Str(_)
+        | AST::Char(_)
+        | AST::Nil
+        | AST::Ident(_)
+        | AST::Quote(_) => ast,
+        AST::Add(left, right) => AST::Add(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Minus(left, right) => AST::Minus(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Mul(left, right) => AST::Mul(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Div(left, right) => AST::Div(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Mod(left, right) => AST::Mod(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Equal(left, right) => AST::Equal(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::NotEqual(left, right) => AST::NotEqual(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::And(left, right) => AST::And(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Or(left, right) => AST::Or(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Not(value) => AST::Not(map_boxed(*value, f)),
+        AST::Negate(value) => AST::Negate(map_boxed(*value, f)),
+        AST::Cons(head, tail) => AST::Cons(map_boxed(*head, f), map_boxed(*tail, f)),
+        AST::Car(value) => AST::Car(map_boxed(*value, f)),
+        AST::Cdr(value) => AST::Cdr(map_boxed(*value, f)),
+        AST::Length(value) => AST::Length(map_boxed(*value, f)),
+        AST::Nth(value, index) => AST::Nth(map_boxed(*value, f), map_boxed(*index, f)),
+        AST::Less(left, right) => AST::Less(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Greater(left, right) => AST::Greater(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::LessEq(left, right) => AST::LessEq(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::GreaterEq(left, right) => AST::GreaterEq(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Min(left, right) => AST::Min(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::Max(left, right) => AST::Max(map_boxed(*left, f), map_boxed(*right, f)),
+        AST::If { cond, then, els } => AST::If {
+            cond: map_boxed(*cond, f),
+            then: map_boxed(*then, f),
+            els: map_boxed(*els, f),
+        },
+        AST::Cond { clauses, default } => AST::Cond {
+            clauses: clauses
+                .into_iter()
+                .map(|(cond, body)| (map_ast(cond, f), map_ast(body, f)))
+                .collect(),
+            default: map_boxed(*default, f),
+        },
+        AST::Case {
+            scrutinee,
+            arms,
+            default,
+        } => AST::Case {
+            scrutinee: map_boxed(*scrutinee, f),
+            arms: arms
+                .into_iter()
+                .map(|(key, body)| (map_ast(key, f), map_ast(body, f)))
+                .collect(),
+            default: map_boxed(*default, f),
+        },
+        AST::While { cond, body } => AST::While {
+            cond: map_boxed(*cond, f),
+            body: map_boxed(*body, f),
+        },
+        AST::Define { name, value } => AST::Define {
+            name,
+            value: map_boxed(*value, f),
+        },
+        AST::Set { name, value } => AST::Set {
+            name,
+            value: map_boxed(*value, f),
+        },
+        AST::Begin(exprs) => AST::Begin(map_all(exprs, f)),
+        AST::Let { name, value, body } => AST::Let {
+            name,
+            value: map_boxed(*value, f),
+            body: map_boxed(*body, f),
+        },
+        AST::LetList { names, value, body } => AST::LetList {
+            names,
+            value: map_boxed(*value, f),
+            body: map_boxed(*body, f),
+        },
+        AST::LetRec { bindings, body } => AST::LetRec {
+            bindings: bindings
+                .into_iter()
+                .map(|(name, value)| (name, map_ast(value, f)))
+                .collect(),
+            body: map_boxed(*body, f),
+        },
+        AST::Function { params, rest, body } => AST::Function {
+            params,
+            rest,
+            body: map_all(body, f),
+        },
+        AST::Apply {
+            fn_lit,
+            args,
+            named,
+        } => AST::Apply {
+            fn_lit: map_boxed(*fn_lit, f),
+            args: map_all(args, f),
+            named: named
+                .into_iter()
+                .map(|(name, arg)| (name, map_ast(arg, f)))
+                .collect(),
+        },
+        AST::ApplyList { fn_lit, args } => AST::ApplyList {
+            fn_lit: map_boxed(*fn_lit, f),
+            args: map_boxed(*args, f),
+        },
+        AST::Print(value) => AST::Print(map_boxed(*value, f)),
+        AST::TypeOf(value) => AST::TypeOf(map_boxed(*value, f)),
+        AST::Assert { cond, msg } => AST::Assert {
+            cond: map_boxed(*cond, f),
+            msg: map_boxed(*msg, f),
+        },
+        AST::List(items) => AST::List(map_all(items, f)),
+        AST::DoTimes { count, var, body } => AST::DoTimes {
+            count: map_boxed(*count, f),
+            var,
+            body: map_boxed(*body, f),
+        },
+        AST::Map { func, list } => AST::Map {
+            func: map_boxed(*func, f),
+            list: map_boxed(*list, f),
+        },
+        AST::Fold { func, init, list } => AST::Fold {
+            func: map_boxed(*func, f),
+            init: map_boxed(*init, f),
+            list: map_boxed(*list, f),
+        },
+    };
+    f(ast)
+}
+
+// 直接の子の式
+// quote の中身のリストも子として数える
+fn children(ast: &AST) -> Vec<&AST> {
+    match ast {
+        AST::Num(_)
+        | AST::Float(_)
+        | AST::Bool(_)
+        | AST::Str(_)
+        | AST::Char(_)
+        | AST::Nil
+        | AST::Ident(_) => {
+            vec![]
+        }
+        AST::Add(left, right)
+        | AST::Minus(left, right)
+        | AST::Mul(left, right)
+        | AST::Div(left, right)
+        | AST::Mod(left, right)
+        | AST::Equal(left, right)
+        | AST::NotEqual(left, right)
+        | AST::And(left, right)
+        | AST::Or(left, right)
+        | AST::Cons(left, right)
+        | AST::Nth(left, right)
+        | AST::Less(left, right)
+        | AST::Greater(left, right)
+        | AST::LessEq(left, right)
+        | AST::GreaterEq(left, right)
+        | AST::Min(left, right)
+        | AST::Max(left, right) => vec![left, right],
+        AST::Not(value)
+        | AST::Negate(value)
+        | AST::Car(value)
+        | AST::Cdr(value)
+        | AST::Length(value)
+        | AST::Print(value)
+        | AST::TypeOf(value)
+        | AST::Quote(value)
+        | AST::Define { value, .. }
+        | AST::Set { value, .. } => vec![value],
+        AST::If { cond, then, els } => vec![cond, then, els],
+        AST::Cond { clauses, default } => clauses
+            .iter()
+            .flat_map(|(cond, body)| [cond, body])
+            .chain([default.as_ref()])
+            .collect(),
+        AST::Case {
+            scrutinee,
+            arms,
+            default,
+        } => std::iter::once(scrutinee.as_ref())
+            .chain(arms.iter().flat_map(|(key, body)| [key, body]))
+            .chain([default.as_ref()])
+            .collect(),
+        AST::While { cond, body } => vec![cond, body],
+        AST::Assert { cond, msg } => vec![cond, msg],
+        AST::Map { func, list } => vec![func, list],
+        AST::DoTimes { count, body, .. } => vec![count, body],
+        AST::Fold { func, init, list } => vec![func, init, list],
+        AST::Let { value, body, .. } | AST::LetList { value, body, .. } => vec![value, body],
+        AST::LetRec { bindings, body } => bindings
+            .iter()
+            .map(|(_, value)| value)
+            .chain([body.as_ref()])
+            .collect(),
+        AST::Begin(exprs) | AST::List(exprs) | AST::Function { body: exprs, .. } => {
+            exprs.iter().collect()
+        }
+        AST::Apply {
+            fn_lit,
+            args,
+            named,
+        } => std::iter::once(fn_lit.as_ref())
+            .chain(args)
+            .chain(named.iter().map(|(_, arg)| arg))
+            .collect(),
+        AST::ApplyList { fn_lit, args } => vec![fn_lit, args],
+    }
+}
+
+// 部分木のどこかに Define があるかどうか
+pub(crate) fn contains_define(ast: &AST) -> bool {
+    matches!(ast, AST::Define { .. }) || children(ast).into_iter().any(contains_define)
+}
+
+// 一番深いところまでのノードの数
+// リテラルや識別子だけなら 1
+pub fn depth(ast: &AST) -> usize {
+    1 + children(ast).into_iter().map(depth).max().unwrap_or(0)
+}
+
+// 木に含まれるノードの数
+pub fn size(ast: &AST) -> usize {
+    1 + children(ast).into_iter().map(size).sum::<usize>()
+}
+
+// 二つのプログラムが同じ形かどうか
+pub fn ast_eq(a: &AST, b: &AST) -> bool {
+    a == b
+}
+
+// 違っていたら、違いを含む一番小さい部分木の組を返す
+// 種類や子の数が同じなら子を順に比べて、最初に違った子の中を探す
+// 子がすべて同じなのに違うとき (Define の名前など) はそのノード自体を返す
+pub fn ast_diff<'a>(a: &'a AST, b: &'a AST) -> Result<(), (&'a AST, &'a AST)> {
+    if a == b {
+        return Ok(());
+    }
+    let (a_children, b_children) = (children(a), children(b));
+    if std::mem::discriminant(a) == std::mem::discriminant(b)
+        && a_children.len() == b_children.len()
+    {
+        for (a_child, b_child) in a_children.into_iter().zip(b_children) {
+            ast_diff(a_child, b_child)?;
+        }
+    }
+    Err((a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast;
+
+    #[test]
+    fn test_map_ast() {
+        let mut inc = |ast| match ast {
+            AST::Num(n) => AST::Num(n + 1),
+            ast => ast,
+        };
+        assert_eq!(map_ast(ast!((+ 1 2)), &mut inc), ast!((+ 2 3)));
+        assert_eq!(
+            map_ast(
+                ast!((Define f (Func (x) (If (== x 0) 1 (Apply f (- x 1)))))),
+                &mut inc
+            ),
+            ast!((Define f (Func (x) (If (== x 1) 2 (Apply f (- x 2))))))
+        );
+        assert_eq!(
+            map_ast(ast!((Cond ((== x 1) 10) (quote (1 2)))), &mut inc),
+            ast!((Cond ((== x 2) 11) (quote (1 2))))
+        );
+
+        let mut rename = |ast| match ast {
+            AST::Ident(id) if id == "x" => AST::Ident("y".to_string()),
+            ast => ast,
+        };
+        assert_eq!(
+            map_ast(ast!((Let z x (Begin (Print x) (+ x z)))), &mut rename),
+            ast!((Let z y (Begin (Print y) (+ y z))))
+        );
+    }
+
+    #[test]
+    fn test_map_ast_order() {
+        // 子が先、親が後に渡される
+        let mut visited = vec![];
+        map_ast(ast!((+ (* 1 2) 3)), &mut |ast| {
+            visited.push(ast.to_string());
+            ast
+        });
+        assert_eq!(visited, vec!["1", "2", "(* 1 2)", "3", "(+ (* 1 2) 3)"]);
+    }
+
+    #[test]
+    fn test_depth_size() {
+        assert_eq!(size(&ast!((+ 1 (+ 2 3)))), 5);
+        assert_eq!(depth(&ast!((+ 1 (+ 2 3)))), 3);
+        assert_eq!(size(&ast!(x)), 1);
+        assert_eq!(depth(&ast!(x)), 1);
+        assert_eq!(size(&ast!((Apply f 1 2))), 4);
+        assert_eq!(depth(&ast!((Func (x) 1 (If x (not x) 2)))), 4);
+        assert_eq!(size(&ast!((Cond ((== x 1) 10) 20))), 6);
+        assert_eq!(depth(&ast!((quote(1(2))))), 4);
+    }
+
+    #[test]
+    fn test_gensym() {
+        let a = gensym("tmp");
+        let b = gensym("tmp");
+        assert_ne!(a, b);
+        assert!(a.starts_with("tmp__"));
+        assert!(b.starts_with("tmp__"));
+        assert!(gensym("loop").starts_with("loop__"));
+
+        // 他のスレッドと同時に呼んでも重ならない
+        let names: Vec<String> = (0..4)
+            .map(|_| std::thread::spawn(|| (0..100).map(|_| gensym("t")).collect::<Vec<_>>()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        let unique: std::collections::HashSet<_> = names.iter().collect();
+        assert_eq!(unique.len(), names.len());
+    }
+
+    #[test]
+    fn test_ast_diff() {
+        let program = ast!((Define f (Func (n) (If (< n 2) n (+ n 1)))));
+        assert!(ast_eq(&program, &program.clone()));
+        assert_eq!(ast_diff(&program, &program.clone()), Ok(()));
+
+        let changed = ast!((Define f (Func (n) (If (< n 2) n (+ n 2)))));
+        assert!(!ast_eq(&program, &changed));
+        assert_eq!(ast_diff(&program, &changed), Err((&ast!(1), &ast!(2))));
+
+        // 種類が違えばその部分木全体
+        assert_eq!(
+            ast_diff(&ast!((+ 1 (* 2 3))), &ast!((+ 1 (- 2 3)))),
+            Err((&ast!((* 2 3)), &ast!((- 2 3))))
+        );
+        // 子の数が違うときも同じ
+        assert_eq!(
+            ast_diff(&ast!((Begin 1 2)), &ast!((Begin 1 2 3))),
+            Err((&ast!((Begin 1 2)), &ast!((Begin 1 2 3))))
+        );
+        // 子が同じなら名前の違うノード自体
+        assert_eq!(
+            ast_diff(&ast!((Define x 1)), &ast!((Define y 1))),
+            Err((&ast!((Define x 1)), &ast!((Define y 1))))
+        );
+        assert_eq!(
+            ast_diff(&ast!((quote (1 (2 3)))), &ast!((quote (1 (2 4))))),
+            Err((&ast!(3), &ast!(4)))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::path::Path;
+use std::process::Command;
+
+fn run(script: &str) -> std::process::Output {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/scripts")
+        .join(script);
+    Command::new(env!("CARGO_BIN_EXE_fmt"))
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_fmt_script() {
+    let output = run("messy.risp");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\
+(Define fib
+  (Func (n) (If (< n 2) n (+ (Apply fib (- n 1)) (Apply fib (- n 2))))))
+
+(Define xs (list 1 2 3))
+
+(Apply fib 10)
+"
+    );
+}
+
+#[test]
+fn test_fmt_idempotent() {
+    let output = run("messy.risp");
+    let formatted = String::from_utf8_lossy(&output.stdout).into_owned();
+    // 同時に走るほかのテストの実行と同じファイルを使わないように pid を名前に含める
+    let path =
+        std::env::temp_dir().join(format!("risp_fmt_idempotent_{}.risp", std::process::id()));
+    std::fs::write(&path, &formatted).unwrap();
+    let again = Command::new(env!("CARGO_BIN_EXE_fmt"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(String::from_utf8_lossy(&again.stdout), formatted);
+}
+
+#[test]
+fn test_fmt_comment() {
+    let output = run("comment.risp");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot format a file with comments"));
+    assert!(stderr.contains("2:18"));
+}
+
+#[test]
+fn test_fmt_parse_error() {
+    let output = run("unterminated.risp");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("parse error"));
+}
+
+#[test]
+fn test_fmt_escapes() {
+    // \r や制御文字は lexer が読めるようにそのまま書き、\n や \" はエスケープしたまま残す
+    let source = "(list \"a\rb\" \"\u{1b}[0m\" \"say \\\"hi\\\"\\n\")";
+    let path = std::env::temp_dir().join(format!("risp_fmt_escapes_{}.risp", std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_fmt"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{}\n", source)
+    );
+}
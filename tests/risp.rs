@@ -0,0 +1,47 @@
+use std::path::Path;
+use std::process::Command;
+
+fn run(script: &str) -> std::process::Output {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/scripts")
+        .join(script);
+    Command::new(env!("CARGO_BIN_EXE_risp"))
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_run_script() {
+    let output = run("sum.risp");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5050\n");
+}
+
+#[test]
+fn test_run_print() {
+    let output = run("print.risp");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n6\n4\n");
+}
+
+#[test]
+fn test_run_missing_file() {
+    let output = run("missing.risp");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.risp"));
+}
+
+#[test]
+fn test_run_parse_error() {
+    let output = run("unterminated.risp");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("parse error"));
+}
+
+#[test]
+fn test_run_prelude() {
+    let output = run("prelude.risp");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "7\n");
+}